@@ -2,6 +2,16 @@
 
 pub mod prelude;
 
+pub mod channel_watchlists;
+pub mod deal_channels;
+pub mod email_attachments;
+pub mod email_digests;
+pub mod email_outbox;
+pub mod email_verifications;
+pub mod notification_deliveries;
 pub mod notification_preferences;
 pub mod price_history;
+pub mod price_history_daily;
+pub mod product_sizes;
 pub mod products;
+pub mod sale_events;
@@ -8,7 +8,7 @@ pub struct Model {
     #[sea_orm(primary_key)]
     pub preference_id: i32,
     pub product_id: i32,
-    pub email: String,
+    pub email: Option<String>,
     pub time_interval_hours: i32,
     pub price_threshold: Decimal,
     pub notify_on_lowest: bool,
@@ -16,10 +16,26 @@ pub struct Model {
     pub last_notified: DateTime,
     pub created_at: DateTime,
     pub updated_at: DateTime,
+    pub watch_size: Option<String>,
+    pub discount_threshold: Option<i32>,
+    pub drop_percent: Option<i32>,
+    pub notify_on_restock: bool,
+    pub periodic_digest: bool,
+    pub discord_user_id: Option<i64>,
+    pub active: bool,
+    pub paused: bool,
+    pub snoozed_until: Option<DateTime>,
+    pub notify_email: bool,
+    pub notify_dm: bool,
+    pub discord_channel_id: Option<i64>,
+    pub webhook_url: Option<String>,
+    pub guild_id: Option<i64>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::notification_deliveries::Entity")]
+    NotificationDeliveries,
     #[sea_orm(
         belongs_to = "super::products::Entity",
         from = "Column::ProductId",
@@ -30,6 +46,12 @@ pub enum Relation {
     Products,
 }
 
+impl Related<super::notification_deliveries::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::NotificationDeliveries.def()
+    }
+}
+
 impl Related<super::products::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::Products.def()
@@ -1,5 +1,15 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.0.0-rc.5
 
+pub use super::channel_watchlists::Entity as ChannelWatchlists;
+pub use super::deal_channels::Entity as DealChannels;
+pub use super::email_attachments::Entity as EmailAttachments;
+pub use super::email_digests::Entity as EmailDigests;
+pub use super::email_outbox::Entity as EmailOutbox;
+pub use super::email_verifications::Entity as EmailVerifications;
+pub use super::notification_deliveries::Entity as NotificationDeliveries;
 pub use super::notification_preferences::Entity as NotificationPreferences;
 pub use super::price_history::Entity as PriceHistory;
+pub use super::price_history_daily::Entity as PriceHistoryDaily;
+pub use super::product_sizes::Entity as ProductSizes;
 pub use super::products::Entity as Products;
+pub use super::sale_events::Entity as SaleEvents;
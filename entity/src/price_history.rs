@@ -10,6 +10,9 @@ pub struct Model {
     pub product_id: i32,
     pub price: Decimal,
     pub recorded_at: DateTime,
+    pub mrp: Option<Decimal>,
+    pub discount_percent: Option<i32>,
+    pub in_stock: bool,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -11,14 +11,33 @@ pub struct Model {
     pub highest_price: Decimal,
     pub lowest_price: Decimal,
     pub last_updated: DateTime,
+    pub mrp: Option<Decimal>,
+    pub discount_percent: Option<i32>,
+    pub in_stock: bool,
+    pub name: Option<String>,
+    pub brand: Option<String>,
+    pub image_url: Option<String>,
+    pub canonical_url: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::channel_watchlists::Entity")]
+    ChannelWatchlists,
     #[sea_orm(has_many = "super::notification_preferences::Entity")]
     NotificationPreferences,
     #[sea_orm(has_many = "super::price_history::Entity")]
     PriceHistory,
+    #[sea_orm(has_many = "super::price_history_daily::Entity")]
+    PriceHistoryDaily,
+    #[sea_orm(has_many = "super::product_sizes::Entity")]
+    ProductSizes,
+}
+
+impl Related<super::channel_watchlists::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ChannelWatchlists.def()
+    }
 }
 
 impl Related<super::notification_preferences::Entity> for Entity {
@@ -33,4 +52,16 @@ impl Related<super::price_history::Entity> for Entity {
     }
 }
 
+impl Related<super::price_history_daily::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PriceHistoryDaily.def()
+    }
+}
+
+impl Related<super::product_sizes::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ProductSizes.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
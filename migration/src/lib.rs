@@ -3,6 +3,23 @@ pub use sea_orm_migration::prelude::*;
 mod m20220101_000001_create_table;
 mod m20250114_103109_price_history;
 mod m20250114_103705_notification_preferences;
+mod m20250210_091500_price_details;
+mod m20250212_174200_product_metadata;
+mod m20250218_083000_notification_rules;
+mod m20250224_102000_notification_deliveries;
+mod m20250303_140000_email_outbox;
+mod m20250310_193000_email_verifications;
+mod m20250317_101500_preference_links;
+mod m20250324_090000_notification_channels;
+mod m20250331_160000_email_attachments;
+mod m20250407_120000_price_history_daily;
+mod m20250414_090000_email_digests;
+mod m20250421_090000_deal_channels;
+mod m20250428_090000_tracker_guilds;
+mod m20250505_090000_channel_watchlists;
+mod m20250512_090000_sale_events;
+mod m20250526_090000_verification_attempts;
+mod m20250602_090000_tracker_owners;
 
 pub struct Migrator;
 
@@ -13,6 +30,23 @@ impl MigratorTrait for Migrator {
             Box::new(m20220101_000001_create_table::Migration),
             Box::new(m20250114_103109_price_history::Migration),
             Box::new(m20250114_103705_notification_preferences::Migration),
+            Box::new(m20250210_091500_price_details::Migration),
+            Box::new(m20250212_174200_product_metadata::Migration),
+            Box::new(m20250218_083000_notification_rules::Migration),
+            Box::new(m20250224_102000_notification_deliveries::Migration),
+            Box::new(m20250303_140000_email_outbox::Migration),
+            Box::new(m20250310_193000_email_verifications::Migration),
+            Box::new(m20250317_101500_preference_links::Migration),
+            Box::new(m20250324_090000_notification_channels::Migration),
+            Box::new(m20250331_160000_email_attachments::Migration),
+            Box::new(m20250407_120000_price_history_daily::Migration),
+            Box::new(m20250414_090000_email_digests::Migration),
+            Box::new(m20250421_090000_deal_channels::Migration),
+            Box::new(m20250428_090000_tracker_guilds::Migration),
+            Box::new(m20250505_090000_channel_watchlists::Migration),
+            Box::new(m20250512_090000_sale_events::Migration),
+            Box::new(m20250526_090000_verification_attempts::Migration),
+            Box::new(m20250602_090000_tracker_owners::Migration),
         ]
     }
 }
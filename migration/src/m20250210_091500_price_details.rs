@@ -0,0 +1,145 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(PriceHistory::Table)
+                    .add_column(decimal_null(PriceHistory::Mrp))
+                    .add_column(integer_null(PriceHistory::DiscountPercent))
+                    .add_column(boolean(PriceHistory::InStock).not_null().default(true))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Products::Table)
+                    .add_column(decimal_null(Products::Mrp))
+                    .add_column(integer_null(Products::DiscountPercent))
+                    .add_column(boolean(Products::InStock).not_null().default(true))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(NotificationPreferences::Table)
+                    .add_column(string_null(NotificationPreferences::WatchSize))
+                    .add_column(integer_null(NotificationPreferences::DiscountThreshold))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(ProductSizes::Table)
+                    .if_not_exists()
+                    .col(pk_auto(ProductSizes::SizeId))
+                    .col(integer(ProductSizes::ProductId).not_null())
+                    .col(string(ProductSizes::Label).not_null())
+                    .col(boolean(ProductSizes::InStock).not_null())
+                    .col(timestamp_null(ProductSizes::RestockedAt))
+                    .col(timestamp(ProductSizes::UpdatedAt).not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-product_sizes-product_id")
+                            .from(ProductSizes::Table, ProductSizes::ProductId)
+                            .to(Products::Table, Products::ProductId)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .index(
+                        Index::create()
+                            .name("idx_product_sizes_product_label")
+                            .table(ProductSizes::Table)
+                            .col(ProductSizes::ProductId)
+                            .col(ProductSizes::Label)
+                            .unique(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ProductSizes::Table).to_owned())
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(NotificationPreferences::Table)
+                    .drop_column(NotificationPreferences::WatchSize)
+                    .drop_column(NotificationPreferences::DiscountThreshold)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Products::Table)
+                    .drop_column(Products::Mrp)
+                    .drop_column(Products::DiscountPercent)
+                    .drop_column(Products::InStock)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(PriceHistory::Table)
+                    .drop_column(PriceHistory::Mrp)
+                    .drop_column(PriceHistory::DiscountPercent)
+                    .drop_column(PriceHistory::InStock)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum PriceHistory {
+    Table,
+    Mrp,
+    DiscountPercent,
+    InStock,
+}
+
+#[derive(DeriveIden)]
+enum Products {
+    Table,
+    ProductId,
+    Mrp,
+    DiscountPercent,
+    InStock,
+}
+
+#[derive(DeriveIden)]
+enum NotificationPreferences {
+    Table,
+    WatchSize,
+    DiscountThreshold,
+}
+
+#[derive(DeriveIden)]
+enum ProductSizes {
+    Table,
+    SizeId,
+    ProductId,
+    Label,
+    InStock,
+    RestockedAt,
+    UpdatedAt,
+}
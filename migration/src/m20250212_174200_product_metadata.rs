@@ -0,0 +1,44 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Products::Table)
+                    .add_column(string_null(Products::Name))
+                    .add_column(string_null(Products::Brand))
+                    .add_column(string_null(Products::ImageUrl))
+                    .add_column(string_null(Products::CanonicalUrl))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Products::Table)
+                    .drop_column(Products::Name)
+                    .drop_column(Products::Brand)
+                    .drop_column(Products::ImageUrl)
+                    .drop_column(Products::CanonicalUrl)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Products {
+    Table,
+    Name,
+    Brand,
+    ImageUrl,
+    CanonicalUrl,
+}
@@ -0,0 +1,49 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(NotificationPreferences::Table)
+                    .add_column(integer_null(NotificationPreferences::DropPercent))
+                    .add_column(
+                        boolean(NotificationPreferences::NotifyOnRestock)
+                            .not_null()
+                            .default(false),
+                    )
+                    .add_column(
+                        boolean(NotificationPreferences::PeriodicDigest)
+                            .not_null()
+                            .default(true),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(NotificationPreferences::Table)
+                    .drop_column(NotificationPreferences::DropPercent)
+                    .drop_column(NotificationPreferences::NotifyOnRestock)
+                    .drop_column(NotificationPreferences::PeriodicDigest)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum NotificationPreferences {
+    Table,
+    DropPercent,
+    NotifyOnRestock,
+    PeriodicDigest,
+}
@@ -0,0 +1,91 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(NotificationDeliveries::Table)
+                    .if_not_exists()
+                    .col(pk_auto(NotificationDeliveries::DeliveryId))
+                    .col(integer(NotificationDeliveries::PreferenceId).not_null())
+                    .col(string(NotificationDeliveries::Trigger).not_null())
+                    .col(string(NotificationDeliveries::EventKey).not_null())
+                    .col(decimal(NotificationDeliveries::Price).not_null())
+                    .col(string(NotificationDeliveries::Channel).not_null())
+                    .col(string(NotificationDeliveries::Status).not_null())
+                    .col(text_null(NotificationDeliveries::Error))
+                    .col(timestamp(NotificationDeliveries::CreatedAt).not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-notification_deliveries-preference_id")
+                            .from(
+                                NotificationDeliveries::Table,
+                                NotificationDeliveries::PreferenceId,
+                            )
+                            .to(
+                                NotificationPreferences::Table,
+                                NotificationPreferences::PreferenceId,
+                            )
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_notification_deliveries_preference_event")
+                    .table(NotificationDeliveries::Table)
+                    .col(NotificationDeliveries::PreferenceId)
+                    .col(NotificationDeliveries::EventKey)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_notification_deliveries_preference_event")
+                    .table(NotificationDeliveries::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(NotificationDeliveries::Table)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum NotificationDeliveries {
+    Table,
+    DeliveryId,
+    PreferenceId,
+    Trigger,
+    EventKey,
+    Price,
+    Channel,
+    Status,
+    Error,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum NotificationPreferences {
+    Table,
+    PreferenceId,
+}
@@ -0,0 +1,106 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(EmailOutbox::Table)
+                    .if_not_exists()
+                    .col(pk_auto(EmailOutbox::OutboxId))
+                    .col(string(EmailOutbox::Recipient).not_null())
+                    .col(string(EmailOutbox::Subject).not_null())
+                    .col(text(EmailOutbox::Body).not_null())
+                    .col(string(EmailOutbox::Status).not_null())
+                    .col(integer(EmailOutbox::Attempts).not_null().default(0))
+                    .col(integer(EmailOutbox::MaxAttempts).not_null())
+                    .col(timestamp(EmailOutbox::NextAttemptAt).not_null())
+                    .col(text_null(EmailOutbox::LastError))
+                    .col(timestamp(EmailOutbox::CreatedAt).not_null())
+                    .col(timestamp_null(EmailOutbox::SentAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_email_outbox_status_next_attempt")
+                    .table(EmailOutbox::Table)
+                    .col(EmailOutbox::Status)
+                    .col(EmailOutbox::NextAttemptAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(NotificationDeliveries::Table)
+                    .add_column(integer_null(NotificationDeliveries::OutboxId))
+                    .add_foreign_key(
+                        TableForeignKey::new()
+                            .name("fk-notification_deliveries-outbox_id")
+                            .from_tbl(NotificationDeliveries::Table)
+                            .from_col(NotificationDeliveries::OutboxId)
+                            .to_tbl(EmailOutbox::Table)
+                            .to_col(EmailOutbox::OutboxId)
+                            .on_delete(ForeignKeyAction::SetNull)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(NotificationDeliveries::Table)
+                    .drop_foreign_key(Alias::new("fk-notification_deliveries-outbox_id"))
+                    .drop_column(NotificationDeliveries::OutboxId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_email_outbox_status_next_attempt")
+                    .table(EmailOutbox::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(EmailOutbox::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum EmailOutbox {
+    Table,
+    OutboxId,
+    Recipient,
+    Subject,
+    Body,
+    Status,
+    Attempts,
+    MaxAttempts,
+    NextAttemptAt,
+    LastError,
+    CreatedAt,
+    SentAt,
+}
+
+#[derive(DeriveIden)]
+enum NotificationDeliveries {
+    Table,
+    OutboxId,
+}
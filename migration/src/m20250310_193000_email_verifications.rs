@@ -0,0 +1,102 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(EmailVerifications::Table)
+                    .if_not_exists()
+                    .col(pk_auto(EmailVerifications::VerificationId))
+                    .col(big_integer(EmailVerifications::DiscordUserId).not_null())
+                    .col(string(EmailVerifications::Email).not_null())
+                    .col(string(EmailVerifications::CodeHash).not_null())
+                    .col(timestamp(EmailVerifications::ExpiresAt).not_null())
+                    .col(timestamp_null(EmailVerifications::VerifiedAt))
+                    .col(timestamp(EmailVerifications::CreatedAt).not_null())
+                    .index(
+                        Index::create()
+                            .name("idx_email_verifications_user_email")
+                            .table(EmailVerifications::Table)
+                            .col(EmailVerifications::DiscordUserId)
+                            .col(EmailVerifications::Email)
+                            .unique(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Existing subscriptions predate verification and stay active
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(NotificationPreferences::Table)
+                    .add_column(big_integer_null(NotificationPreferences::DiscordUserId))
+                    .add_column(
+                        boolean(NotificationPreferences::Active)
+                            .not_null()
+                            .default(true),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_notification_preferences_discord_user_id")
+                    .table(NotificationPreferences::Table)
+                    .col(NotificationPreferences::DiscordUserId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_notification_preferences_discord_user_id")
+                    .table(NotificationPreferences::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(NotificationPreferences::Table)
+                    .drop_column(NotificationPreferences::DiscordUserId)
+                    .drop_column(NotificationPreferences::Active)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(EmailVerifications::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum EmailVerifications {
+    Table,
+    VerificationId,
+    DiscordUserId,
+    Email,
+    CodeHash,
+    ExpiresAt,
+    VerifiedAt,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum NotificationPreferences {
+    Table,
+    DiscordUserId,
+    Active,
+}
@@ -0,0 +1,66 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(NotificationPreferences::Table)
+                    .add_column(
+                        boolean(NotificationPreferences::Paused)
+                            .not_null()
+                            .default(false),
+                    )
+                    .add_column(timestamp_null(NotificationPreferences::SnoozedUntil))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(EmailOutbox::Table)
+                    .add_column(string_null(EmailOutbox::UnsubscribeUrl))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(EmailOutbox::Table)
+                    .drop_column(EmailOutbox::UnsubscribeUrl)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(NotificationPreferences::Table)
+                    .drop_column(NotificationPreferences::Paused)
+                    .drop_column(NotificationPreferences::SnoozedUntil)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum NotificationPreferences {
+    Table,
+    Paused,
+    SnoozedUntil,
+}
+
+#[derive(DeriveIden)]
+enum EmailOutbox {
+    Table,
+    UnsubscribeUrl,
+}
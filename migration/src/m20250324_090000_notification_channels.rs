@@ -0,0 +1,64 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Discord-only trackers have no email address
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(NotificationPreferences::Table)
+                    .modify_column(
+                        ColumnDef::new(NotificationPreferences::Email)
+                            .string()
+                            .null(),
+                    )
+                    .add_column(
+                        boolean(NotificationPreferences::NotifyEmail)
+                            .not_null()
+                            .default(true),
+                    )
+                    .add_column(
+                        boolean(NotificationPreferences::NotifyDm)
+                            .not_null()
+                            .default(false),
+                    )
+                    .add_column(big_integer_null(NotificationPreferences::DiscordChannelId))
+                    .add_column(string_null(NotificationPreferences::WebhookUrl))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(NotificationPreferences::Table)
+                    .drop_column(NotificationPreferences::NotifyEmail)
+                    .drop_column(NotificationPreferences::NotifyDm)
+                    .drop_column(NotificationPreferences::DiscordChannelId)
+                    .drop_column(NotificationPreferences::WebhookUrl)
+                    .modify_column(
+                        ColumnDef::new(NotificationPreferences::Email)
+                            .string()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum NotificationPreferences {
+    Table,
+    Email,
+    NotifyEmail,
+    NotifyDm,
+    DiscordChannelId,
+    WebhookUrl,
+}
@@ -0,0 +1,63 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(EmailAttachments::Table)
+                    .if_not_exists()
+                    .col(pk_auto(EmailAttachments::AttachmentId))
+                    .col(integer(EmailAttachments::OutboxId).not_null())
+                    .col(string(EmailAttachments::ContentId).not_null())
+                    .col(string(EmailAttachments::ContentType).not_null())
+                    .col(blob(EmailAttachments::Data).not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-email_attachments-outbox_id")
+                            .from(EmailAttachments::Table, EmailAttachments::OutboxId)
+                            .to(EmailOutbox::Table, EmailOutbox::OutboxId)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_email_attachments_outbox_id")
+                    .table(EmailAttachments::Table)
+                    .col(EmailAttachments::OutboxId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(EmailAttachments::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum EmailAttachments {
+    Table,
+    AttachmentId,
+    OutboxId,
+    ContentId,
+    ContentType,
+    Data,
+}
+
+#[derive(DeriveIden)]
+enum EmailOutbox {
+    Table,
+    OutboxId,
+}
@@ -0,0 +1,94 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(PriceHistoryDaily::Table)
+                    .if_not_exists()
+                    .col(pk_auto(PriceHistoryDaily::DailyId))
+                    .col(integer(PriceHistoryDaily::ProductId).not_null())
+                    .col(date(PriceHistoryDaily::Day).not_null())
+                    .col(decimal(PriceHistoryDaily::MinPrice).not_null())
+                    .col(decimal(PriceHistoryDaily::MaxPrice).not_null())
+                    .col(decimal(PriceHistoryDaily::ClosePrice).not_null())
+                    .col(integer(PriceHistoryDaily::Samples).not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-price_history_daily-product_id")
+                            .from(PriceHistoryDaily::Table, PriceHistoryDaily::ProductId)
+                            .to(Products::Table, Products::ProductId)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .index(
+                        Index::create()
+                            .name("idx_price_history_daily_product_day")
+                            .table(PriceHistoryDaily::Table)
+                            .col(PriceHistoryDaily::ProductId)
+                            .col(PriceHistoryDaily::Day)
+                            .unique(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // The retention job and history reads filter on product and time
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_price_history_product_recorded_at")
+                    .table(PriceHistory::Table)
+                    .col(PriceHistory::ProductId)
+                    .col(PriceHistory::RecordedAt)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_price_history_product_recorded_at")
+                    .table(PriceHistory::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(PriceHistoryDaily::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum PriceHistoryDaily {
+    Table,
+    DailyId,
+    ProductId,
+    Day,
+    MinPrice,
+    MaxPrice,
+    ClosePrice,
+    Samples,
+}
+
+#[derive(DeriveIden)]
+enum PriceHistory {
+    Table,
+    ProductId,
+    RecordedAt,
+}
+
+#[derive(DeriveIden)]
+enum Products {
+    Table,
+    ProductId,
+}
@@ -0,0 +1,39 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(EmailDigests::Table)
+                    .if_not_exists()
+                    .col(pk_auto(EmailDigests::DigestId))
+                    .col(string(EmailDigests::Email).not_null().unique_key())
+                    .col(string(EmailDigests::Cadence).not_null())
+                    .col(timestamp(EmailDigests::LastSentAt).not_null())
+                    .col(timestamp(EmailDigests::CreatedAt).not_null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(EmailDigests::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum EmailDigests {
+    Table,
+    DigestId,
+    Email,
+    Cadence,
+    LastSentAt,
+    CreatedAt,
+}
@@ -0,0 +1,40 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // One deals channel per guild
+        manager
+            .create_table(
+                Table::create()
+                    .table(DealChannels::Table)
+                    .if_not_exists()
+                    .col(big_integer(DealChannels::GuildId).primary_key())
+                    .col(big_integer(DealChannels::ChannelId).not_null())
+                    .col(integer(DealChannels::DropPercent).not_null())
+                    .col(boolean(DealChannels::AnnounceLows).not_null().default(true))
+                    .col(timestamp(DealChannels::UpdatedAt).not_null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(DealChannels::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum DealChannels {
+    Table,
+    GuildId,
+    ChannelId,
+    DropPercent,
+    AnnounceLows,
+    UpdatedAt,
+}
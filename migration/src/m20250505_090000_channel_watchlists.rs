@@ -0,0 +1,78 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Products a channel watches together; price events are posted in the channel
+        manager
+            .create_table(
+                Table::create()
+                    .table(ChannelWatchlists::Table)
+                    .if_not_exists()
+                    .col(pk_auto(ChannelWatchlists::WatchlistId))
+                    .col(big_integer(ChannelWatchlists::ChannelId).not_null())
+                    .col(big_integer(ChannelWatchlists::GuildId).not_null())
+                    .col(integer(ChannelWatchlists::ProductId).not_null())
+                    .col(big_integer(ChannelWatchlists::AddedBy).not_null())
+                    .col(timestamp(ChannelWatchlists::CreatedAt).not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-channel_watchlists-product_id")
+                            .from(ChannelWatchlists::Table, ChannelWatchlists::ProductId)
+                            .to(Products::Table, Products::ProductId)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_channel_watchlists_channel_product")
+                    .table(ChannelWatchlists::Table)
+                    .col(ChannelWatchlists::ChannelId)
+                    .col(ChannelWatchlists::ProductId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_channel_watchlists_product_id")
+                    .table(ChannelWatchlists::Table)
+                    .col(ChannelWatchlists::ProductId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ChannelWatchlists::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum ChannelWatchlists {
+    Table,
+    WatchlistId,
+    ChannelId,
+    GuildId,
+    ProductId,
+    AddedBy,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Products {
+    Table,
+    ProductId,
+}
@@ -0,0 +1,52 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Store-wide sales seen by the scraper; the open one has no `ended_at`
+        manager
+            .create_table(
+                Table::create()
+                    .table(SaleEvents::Table)
+                    .if_not_exists()
+                    .col(pk_auto(SaleEvents::SaleId))
+                    .col(timestamp(SaleEvents::StartedAt).not_null())
+                    .col(timestamp_null(SaleEvents::EndedAt))
+                    .col(integer(SaleEvents::ProductsScraped).not_null())
+                    .col(integer(SaleEvents::ProductsDropped).not_null())
+                    .col(decimal(SaleEvents::AverageDropPercent).not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_sale_events_started_at")
+                    .table(SaleEvents::Table)
+                    .col(SaleEvents::StartedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(SaleEvents::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum SaleEvents {
+    Table,
+    SaleId,
+    StartedAt,
+    EndedAt,
+    ProductsScraped,
+    ProductsDropped,
+    AverageDropPercent,
+}
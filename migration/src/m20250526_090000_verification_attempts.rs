@@ -0,0 +1,75 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Wrong guesses against the pending code, and when the last code went out so an
+        // address is not sent one on every `/myntra`
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(EmailVerifications::Table)
+                    .add_column(integer(EmailVerifications::FailedAttempts).default(0))
+                    .add_column(
+                        timestamp(EmailVerifications::SentAt).default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_email_verifications_email")
+                    .table(EmailVerifications::Table)
+                    .col(EmailVerifications::Email)
+                    .to_owned(),
+            )
+            .await?;
+
+        // Codes still pending were hashed without the server secret and can no longer match
+        manager
+            .exec_stmt(
+                Query::update()
+                    .table(EmailVerifications::Table)
+                    .value(EmailVerifications::ExpiresAt, Expr::current_timestamp())
+                    .and_where(Expr::col(EmailVerifications::VerifiedAt).is_null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_email_verifications_email")
+                    .table(EmailVerifications::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(EmailVerifications::Table)
+                    .drop_column(EmailVerifications::FailedAttempts)
+                    .drop_column(EmailVerifications::SentAt)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum EmailVerifications {
+    Table,
+    Email,
+    FailedAttempts,
+    SentAt,
+    ExpiresAt,
+    VerifiedAt,
+}
@@ -0,0 +1,33 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Trackers from before Discord users were recorded belong to whoever verified their
+        // email, when exactly one user did. The rest are claimed on the next verification.
+        manager
+            .get_connection()
+            .execute_unprepared(
+                "UPDATE notification_preferences AS p
+                 SET discord_user_id = v.discord_user_id
+                 FROM (
+                     SELECT email, MIN(discord_user_id) AS discord_user_id
+                     FROM email_verifications
+                     WHERE verified_at IS NOT NULL
+                     GROUP BY email
+                     HAVING COUNT(DISTINCT discord_user_id) = 1
+                 ) AS v
+                 WHERE p.discord_user_id IS NULL AND p.email = v.email",
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        // Which trackers were backfilled is not recorded
+        Ok(())
+    }
+}
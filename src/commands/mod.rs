@@ -1,8 +1,10 @@
 pub mod cargocut;
 pub mod id;
 pub mod moderate;
+pub mod outbox;
 pub mod ping;
 pub mod scrape;
+pub mod scraper;
 pub mod welcome_message;
 pub mod wonderful_command;
 pub mod youtube;
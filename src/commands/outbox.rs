@@ -0,0 +1,122 @@
+use crate::cron::outbox::{recent_failures, requeue, requeue_all_failed};
+use crate::utils::util::Response;
+use sea_orm::DatabaseConnection;
+use serenity::all::{
+    Color, CommandOptionType, CreateCommand, CreateCommandOption, CreateEmbed, Permissions,
+    ResolvedOption, ResolvedValue,
+};
+use tracing::error;
+
+const FAILURES_SHOWN: u64 = 10;
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("outbox")
+        .description("Inspect and retry outgoing emails")
+        .default_member_permissions(Permissions::ADMINISTRATOR)
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "failed",
+            "List emails that permanently failed",
+        ))
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "requeue",
+                "Retry a failed email",
+            )
+            .add_sub_option(
+                CreateCommandOption::new(CommandOptionType::Integer, "id", "Outbox id")
+                    .required(true),
+            ),
+        )
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "requeueall",
+            "Retry every failed email",
+        ))
+}
+
+pub async fn run(options: &[ResolvedOption<'_>], db: &DatabaseConnection) -> Response {
+    let Some(ResolvedOption {
+        name,
+        value: ResolvedValue::SubCommand(sub_options),
+        ..
+    }) = options.first()
+    else {
+        return "Please choose a subcommand".to_string().into();
+    };
+
+    match *name {
+        "failed" => list_failed(db).await,
+        "requeue" => {
+            let Some(ResolvedOption {
+                value: ResolvedValue::Integer(id),
+                ..
+            }) = sub_options.first()
+            else {
+                return "Please provide an outbox id".to_string().into();
+            };
+            match requeue(db, *id as i32).await {
+                Ok(true) => format!("📬 Email #{} queued for another attempt", id).into(),
+                Ok(false) => format!("Email #{} is not a failed message", id).into(),
+                Err(e) => {
+                    error!("Failed to requeue email {}: {}", id, e);
+                    "Could not requeue that email".to_string().into()
+                }
+            }
+        }
+        "requeueall" => match requeue_all_failed(db).await {
+            Ok(count) => format!("📬 {} failed emails queued for another attempt", count).into(),
+            Err(e) => {
+                error!("Failed to requeue emails: {}", e);
+                "Could not requeue failed emails".to_string().into()
+            }
+        },
+        _ => "not implemented :(".to_string().into(),
+    }
+}
+
+async fn list_failed(db: &DatabaseConnection) -> Response {
+    let failures = match recent_failures(db, FAILURES_SHOWN).await {
+        Ok(failures) => failures,
+        Err(e) => {
+            error!("Failed to load outbox failures: {}", e);
+            return "Could not load the outbox".to_string().into();
+        }
+    };
+
+    if failures.is_empty() {
+        return "✅ No failed emails".to_string().into();
+    }
+
+    let mut embed = CreateEmbed::default()
+        .title("Failed emails")
+        .description(format!(
+            "Showing the {} most recent. Use `/outbox requeue` to retry one.",
+            failures.len()
+        ))
+        .color(Color::RED);
+
+    for message in failures {
+        let last_error: String = message
+            .last_error
+            .unwrap_or_default()
+            .chars()
+            .take(200)
+            .collect();
+        embed = embed.field(
+            format!("#{} → {}", message.outbox_id, message.recipient),
+            format!(
+                "{}\nAttempts: {}/{} · created {}\n`{}`",
+                message.subject,
+                message.attempts,
+                message.max_attempts,
+                message.created_at.format("%Y-%m-%d %H:%M"),
+                last_error
+            ),
+            false,
+        );
+    }
+
+    embed.into()
+}
@@ -0,0 +1,174 @@
+use crate::commands::scrape::myntra::{track_from_deal, Tracked};
+use crate::scraper::deals::TRACK_BUTTON_PREFIX;
+use crate::utils::embeds::product_embed;
+use ::entity::deal_channels;
+use sea_orm::sea_query::OnConflict;
+use sea_orm::*;
+use serenity::all::{
+    CommandInteraction, CommandOptionType, ComponentInteraction, Context, CreateCommand,
+    CreateCommandOption, EditInteractionResponse, Permissions, ResolvedOption, ResolvedValue,
+};
+use tracing::error;
+
+const DEFAULT_DROP_PERCENT: i64 = 20;
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("deals")
+        .description("Announce big Myntra price drops in a deals channel")
+        .default_member_permissions(Permissions::MANAGE_GUILD)
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "here",
+                "Post deals in this channel",
+            )
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::Integer,
+                    "droppercent",
+                    "Smallest drop in percent worth a post (default 20)",
+                )
+                .min_int_value(1)
+                .max_int_value(95),
+            )
+            .add_sub_option(CreateCommandOption::new(
+                CommandOptionType::Boolean,
+                "alltimelows",
+                "Also post every new all-time low (default true)",
+            )),
+        )
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "off",
+            "Stop posting deals in this server",
+        ))
+}
+
+pub async fn run(command: &CommandInteraction, db: &DatabaseConnection) -> String {
+    let Some(guild_id) = command.guild_id else {
+        return "Deals can only be posted in a server".to_string();
+    };
+    let guild_id = guild_id.get() as i64;
+    let options = command.data.options();
+    let Some(ResolvedOption {
+        name,
+        value: ResolvedValue::SubCommand(sub_options),
+        ..
+    }) = options.first()
+    else {
+        return "Please choose a subcommand".to_string();
+    };
+
+    match *name {
+        "here" => {
+            let mut drop_percent = DEFAULT_DROP_PERCENT;
+            let mut announce_lows = true;
+            for option in sub_options {
+                match (option.name, &option.value) {
+                    ("droppercent", ResolvedValue::Integer(percent)) => drop_percent = *percent,
+                    ("alltimelows", ResolvedValue::Boolean(lows)) => announce_lows = *lows,
+                    _ => {}
+                }
+            }
+
+            let channel = deal_channels::ActiveModel {
+                guild_id: Set(guild_id),
+                channel_id: Set(command.channel_id.get() as i64),
+                drop_percent: Set(drop_percent as i32),
+                announce_lows: Set(announce_lows),
+                updated_at: Set(chrono::Utc::now().naive_utc()),
+            };
+            let saved = deal_channels::Entity::insert(channel)
+                .on_conflict(
+                    OnConflict::column(deal_channels::Column::GuildId)
+                        .update_columns([
+                            deal_channels::Column::ChannelId,
+                            deal_channels::Column::DropPercent,
+                            deal_channels::Column::AnnounceLows,
+                            deal_channels::Column::UpdatedAt,
+                        ])
+                        .to_owned(),
+                )
+                .exec(db)
+                .await;
+            match saved {
+                Ok(_) => format!(
+                    "🛍️ Tracked products that drop {}% or more{} will be posted in <#{}>.",
+                    drop_percent,
+                    if announce_lows {
+                        " or hit an all-time low"
+                    } else {
+                        ""
+                    },
+                    command.channel_id
+                ),
+                Err(e) => {
+                    error!("Failed to save deal channel: {}", e);
+                    "Could not set up the deals channel. Please try again later.".to_string()
+                }
+            }
+        }
+        "off" => match deal_channels::Entity::delete_by_id(guild_id).exec(db).await {
+            Ok(result) if result.rows_affected > 0 => {
+                "🔕 Deals are no longer posted in this server.".to_string()
+            }
+            Ok(_) => "This server has no deals channel.".to_string(),
+            Err(e) => {
+                error!("Failed to remove deal channel: {}", e);
+                "Could not turn deals off. Please try again later.".to_string()
+            }
+        },
+        _ => "not implemented :(".to_string(),
+    }
+}
+
+/// Handles the "Track this" button under a deal post. Replies only to the member who
+/// pressed it.
+pub async fn track_button(
+    ctx: &Context,
+    component: &ComponentInteraction,
+    db: &DatabaseConnection,
+) {
+    // A product that has left the table is scraped again, which Discord does not wait for
+    if let Err(why) = component.defer_ephemeral(&ctx.http).await {
+        error!("Cannot defer deal button: {why}");
+        return;
+    }
+
+    let product_id = component
+        .data
+        .custom_id
+        .strip_prefix(TRACK_BUTTON_PREFIX)
+        .and_then(|id| id.parse::<i32>().ok());
+
+    let response = EditInteractionResponse::new();
+    let response = match product_id {
+        None => response.content("This deal can no longer be tracked."),
+        Some(product_id) => {
+            let roles = component
+                .member
+                .as_ref()
+                .map(|member| member.roles.clone())
+                .unwrap_or_default();
+            let guild_id = component.guild_id.map(|guild_id| guild_id.get() as i64);
+            match track_from_deal(db, product_id, component.user.id, guild_id, &roles).await {
+                Err(message) => response.content(message),
+                Ok(Ok(Some(Tracked::Started(product)))) => response.embed(product_embed(&product).description(
+                    "✅ Tracking started. Alerts for this product go to your DMs; change them with `/edittracker`.",
+                )),
+                Ok(Ok(Some(Tracked::Existing(product)))) => response.embed(product_embed(&product).description(
+                    "ℹ️ You already track this product in your DMs; change the alerts with `/edittracker`.",
+                )),
+                Ok(Ok(None)) => response.content("❌ This product is no longer on Myntra."),
+                Ok(Err(e)) => {
+                    error!("Error tracking product {} from a deal: {:?}", product_id, e);
+                    response.content("Something went wrong while setting up your tracker. Please try again later.")
+                }
+            }
+        }
+    };
+
+    if let Err(why) = component.edit_response(&ctx.http, response).await {
+        error!("Cannot respond to deal button: {why}");
+    }
+}
@@ -0,0 +1,81 @@
+use crate::cron::digests::{set_cadence, Cadence};
+use ::entity::notification_preferences;
+use sea_orm::*;
+use serenity::all::{
+    CommandInteraction, CommandOptionType, CreateCommand, CreateCommandOption, ResolvedOption,
+    ResolvedValue,
+};
+use tracing::error;
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("emaildigest")
+        .description("Get your email alerts bundled into one summary email")
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "cadence",
+                "How often to send the summary",
+            )
+            .required(true)
+            .add_string_choice("Daily", "daily")
+            .add_string_choice("Weekly", "weekly")
+            .add_string_choice("Off, one email per alert", "off"),
+        )
+}
+
+pub async fn run(command: &CommandInteraction, db: &DatabaseConnection) -> String {
+    let cadence = match command.data.options().first() {
+        Some(ResolvedOption {
+            value: ResolvedValue::String("off"),
+            ..
+        }) => None,
+        Some(ResolvedOption {
+            value: ResolvedValue::String(cadence),
+            ..
+        }) => match Cadence::parse(cadence) {
+            Some(cadence) => Some(cadence),
+            None => return "Please choose daily, weekly or off".to_string(),
+        },
+        _ => return "Please choose daily, weekly or off".to_string(),
+    };
+
+    // Only addresses already in use on the user's own, verified trackers
+    let emails: Vec<String> = match notification_preferences::Entity::find()
+        .select_only()
+        .column(notification_preferences::Column::Email)
+        .distinct()
+        .filter(notification_preferences::Column::DiscordUserId.eq(command.user.id.get() as i64))
+        .filter(notification_preferences::Column::Active.eq(true))
+        .filter(notification_preferences::Column::Email.is_not_null())
+        .into_tuple::<String>()
+        .all(db)
+        .await
+    {
+        Ok(emails) => emails,
+        Err(e) => {
+            error!("Failed to load tracker emails: {}", e);
+            return "Could not update your digest. Please try again later.".to_string();
+        }
+    };
+
+    if emails.is_empty() {
+        return "You have no email alerts yet. Add an email with `/myntra` first.".to_string();
+    }
+
+    if let Err(e) = set_cadence(db, &emails, cadence).await {
+        error!("Failed to set digest cadence: {}", e);
+        return "Could not update your digest. Please try again later.".to_string();
+    }
+
+    match cadence {
+        Some(cadence) => format!(
+            "📬 Email alerts to {} will now arrive as one {} summary, sorted by the biggest price changes.",
+            emails.join(", "),
+            cadence.as_str()
+        ),
+        None => format!(
+            "📨 Email alerts to {} will be sent one by one again. Alerts waiting for the next summary go out within the hour.",
+            emails.join(", ")
+        ),
+    }
+}
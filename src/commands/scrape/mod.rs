@@ -1 +1,7 @@
+pub mod deals;
+pub mod digest;
 pub mod myntra;
+pub mod price_history;
+pub mod trackers;
+pub mod verify_email;
+pub mod watchlist;
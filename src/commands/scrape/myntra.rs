@@ -1,130 +1,503 @@
-use crate::scraper::myntra::scrape_products;
-use anyhow::Context;
+use crate::config::quotas::QuotaConfig;
+use crate::scraper::myntra::{resolve_product_id, scrape_products};
+use crate::scraper::price_scraper::update_sizes;
+use crate::utils::emails::verify_email::{
+    is_verified, start_verification, verified_email_for, Verification,
+};
+use crate::utils::embeds::product_embed;
+use crate::utils::util::{create_ephemeral_response, edit_response, Response};
+use crate::utils::webhooks::validate_url;
+use anyhow::Context as _;
 use entity::{notification_preferences, products};
 use sea_orm::TransactionTrait;
-use sea_orm::{prelude::Decimal, sqlx::types::chrono::Utc, DatabaseConnection, EntityTrait, Set};
+use sea_orm::{
+    prelude::Decimal, sqlx::types::chrono::Utc, ColumnTrait, DatabaseConnection, EntityTrait,
+    PaginatorTrait, QueryFilter, Set,
+};
 use serenity::all::{
-    CommandOptionType, CreateCommand, CreateCommandOption, ResolvedOption, ResolvedValue,
+    CommandInteraction, CommandOptionType, Context, CreateCommand, CreateCommandOption,
+    ResolvedValue, RoleId, UserId,
 };
-use std::str::FromStr;
 use tracing::error;
-pub async fn myntra_add(
-    options: &[ResolvedOption<'_>],
-    db: &DatabaseConnection,
-) -> Result<String, Box<dyn std::error::Error>> {
-    let product_id = match options.first() {
-        Some(ResolvedOption {
-            value: ResolvedValue::Number(id),
-            ..
-        }) => *id as i32,
-        _ => return Ok("Please provide a valid ProductId".to_string()),
+
+const DEFAULT_INTERVAL_HOURS: i32 = 24;
+
+pub enum Tracked {
+    Started(products::Model),
+    /// The same tracker existed already and was left as it is.
+    Existing(products::Model),
+}
+
+struct TrackRequest {
+    product_id: i32,
+    email: Option<String>,
+    discord_user_id: i64,
+    guild_id: Option<i64>,
+    active: bool,
+    notify_dm: bool,
+    discord_channel_id: Option<i64>,
+    webhook_url: Option<String>,
+    time_interval: i32,
+    price_threshold: Decimal,
+    notify_on_lowest: bool,
+    notify_on_highest: bool,
+    watch_size: Option<String>,
+    discount_threshold: Option<i32>,
+    drop_percent: Option<i32>,
+    notify_on_restock: bool,
+    periodic_digest: bool,
+}
+
+pub async fn myntra_add(ctx: &Context, command: &CommandInteraction, db: &DatabaseConnection) {
+    let mut product = None;
+    let mut email = None;
+    let mut time_interval = None;
+    let mut price_threshold = Decimal::new(0, 0);
+    let mut notify_on_lowest = false;
+    let mut notify_on_highest = false;
+    let mut watch_size = None;
+    let mut discount_threshold = None;
+    let mut drop_percent = None;
+    let mut notify_on_restock = false;
+    let mut periodic_digest = true;
+    let mut notify_dm = false;
+    let mut post_in_channel = false;
+    let mut webhook_url = None;
+
+    for option in command.data.options() {
+        match (option.name, &option.value) {
+            ("product", ResolvedValue::String(link)) => product = Some(link.to_string()),
+            ("email", ResolvedValue::String(address)) => email = Some(address.to_string()),
+            ("timeintreval", ResolvedValue::Number(hours)) => time_interval = Some(*hours as i32),
+            ("pricethreshold", ResolvedValue::Number(threshold)) => {
+                match Decimal::try_from(*threshold) {
+                    Ok(threshold) => price_threshold = threshold,
+                    Err(_) => {
+                        create_ephemeral_response(
+                            ctx,
+                            command,
+                            "Invalid price threshold".to_string(),
+                        )
+                        .await;
+                        return;
+                    }
+                }
+            }
+            ("notifyonlowest", ResolvedValue::Boolean(notify)) => notify_on_lowest = *notify,
+            ("notifyonhighest", ResolvedValue::Boolean(notify)) => notify_on_highest = *notify,
+            ("size", ResolvedValue::String(size)) => watch_size = Some(size.trim().to_string()),
+            ("discountpercent", ResolvedValue::Integer(percent)) => {
+                discount_threshold = Some(*percent as i32)
+            }
+            ("droppercent", ResolvedValue::Integer(percent)) => {
+                drop_percent = Some(*percent as i32)
+            }
+            ("notifyonrestock", ResolvedValue::Boolean(notify)) => notify_on_restock = *notify,
+            ("digest", ResolvedValue::Boolean(digest)) => periodic_digest = *digest,
+            ("dm", ResolvedValue::Boolean(dm)) => notify_dm = *dm,
+            ("postinchannel", ResolvedValue::Boolean(post)) => post_in_channel = *post,
+            ("webhook", ResolvedValue::String(url)) => webhook_url = Some(url.trim().to_string()),
+            _ => {}
+        }
+    }
+
+    let Some(product) = product else {
+        create_ephemeral_response(
+            ctx,
+            command,
+            "Please provide a Myntra product link".to_string(),
+        )
+        .await;
+        return;
     };
 
-    let email = match options.get(1) {
-        Some(ResolvedOption {
-            value: ResolvedValue::String(email),
-            ..
-        }) => email.to_string(),
-        _ => return Ok("Please provide a valid email address".to_string()),
+    let quotas = QuotaConfig::get();
+    let time_interval = match time_interval {
+        Some(hours) => {
+            if let Err(message) = quotas.check_interval(hours) {
+                create_ephemeral_response(ctx, command, message).await;
+                return;
+            }
+            hours
+        }
+        None => DEFAULT_INTERVAL_HOURS.max(quotas.min_interval_hours()),
     };
 
-    let time_interval = match options.get(2) {
-        Some(ResolvedOption {
-            value: ResolvedValue::Number(hours),
-            ..
-        }) => *hours as i32,
-        _ => 24,
+    // Channel posts reach everyone in it, so only people who run the channel may set them up
+    let discord_channel_id = if post_in_channel {
+        let can_manage = command
+            .member
+            .as_ref()
+            .and_then(|member| member.permissions)
+            .is_some_and(|permissions| permissions.manage_channels());
+        if !can_manage {
+            create_ephemeral_response(
+                ctx,
+                command,
+                "You need the Manage Channels permission to post alerts in this channel"
+                    .to_string(),
+            )
+            .await;
+            return;
+        }
+        Some(command.channel_id.get() as i64)
+    } else {
+        None
     };
 
-    let price_threshold = match options.get(3) {
-        Some(ResolvedOption {
-            value: ResolvedValue::Number(threshold),
-            ..
-        }) => Decimal::try_from(*threshold).context("Invalid price threshold")?,
-        _ => Decimal::new(0, 0),
+    if let Some(url) = &webhook_url {
+        if let Err(message) = validate_url(url).await {
+            create_ephemeral_response(ctx, command, message).await;
+            return;
+        }
+    }
+
+    let guild_id = command.guild_id.map(|guild_id| guild_id.get() as i64);
+    let roles = command
+        .member
+        .as_ref()
+        .map(|member| member.roles.clone())
+        .unwrap_or_default();
+    let allowed = match quotas
+        .check_limits(db, command.user.id, guild_id, &roles)
+        .await
+    {
+        Ok(()) => quotas.check_cooldown(command.user.id),
+        Err(message) => Err(message),
     };
+    if let Err(message) = allowed {
+        create_ephemeral_response(ctx, command, message).await;
+        return;
+    }
 
-    let notify_on_lowest = match options.get(4) {
-        Some(ResolvedOption {
-            value: ResolvedValue::Boolean(notify),
-            ..
-        }) => *notify,
-        _ => false,
+    let discord_user_id = command.user.id.get() as i64;
+    let has_other_destination = notify_dm || discord_channel_id.is_some() || webhook_url.is_some();
+    let email = match email {
+        Some(email) => Some(email),
+        None if has_other_destination => None,
+        // Fall back to the address this user verified before
+        None => match verified_email_for(db, discord_user_id).await {
+            Ok(Some(email)) => Some(email),
+            Ok(None) => {
+                create_ephemeral_response(
+                    ctx,
+                    command,
+                    "Please provide an email address, or choose `dm`, `postinchannel` or `webhook` for your alerts".to_string(),
+                )
+                .await;
+                return;
+            }
+            Err(e) => {
+                error!("Error looking up verified email: {}", e);
+                create_ephemeral_response(
+                    ctx,
+                    command,
+                    "Something went wrong. Please try again later.".to_string(),
+                )
+                .await;
+                return;
+            }
+        },
+    };
+    let email = match email.map(|email| email.trim().parse::<lettre::Address>()) {
+        None => None,
+        Some(Ok(address)) => Some(address.to_string().to_lowercase()),
+        Some(Err(_)) => {
+            create_ephemeral_response(
+                ctx,
+                command,
+                "Please provide a valid email address".to_string(),
+            )
+            .await;
+            return;
+        }
     };
 
-    let db = db.clone();
+    // Scraping takes a few seconds, longer than Discord waits for the first reply. The
+    // replies name the member's email address, so only they see them.
+    if let Err(why) = command.defer_ephemeral(&ctx.http).await {
+        error!("Cannot defer myntra command: {why}");
+        return;
+    }
 
-    tokio::spawn(async move {
-        let result: Result<_, Box<dyn std::error::Error>> = async {
-            let product_price = scrape_products(vec![product_id])
-                .await?
-                .first()
-                .unwrap()
-                .to_owned();
-            let txn = db.begin().await.context("Failed to start transaction")?;
+    let product_id = match resolve_product_id(&product).await {
+        Ok(id) => id,
+        Err(message) => {
+            edit_response(ctx, command, message).await;
+            return;
+        }
+    };
 
-            // First try to insert the product if it doesn't exist
-            let product_model = products::ActiveModel {
-                product_id: Set(product_id),
-                current_price: Set(
-                    Decimal::from_str(&product_price).context("Invalid product price")?
-                ),
-                highest_price: Set(
-                    Decimal::from_str(&product_price).context("Invalid product price")?
-                ),
-                lowest_price: Set(
-                    Decimal::from_str(&product_price).context("Invalid product price")?
-                ),
-                last_updated: Set(Utc::now().naive_utc()),
-                ..Default::default()
-            };
+    let verified = match &email {
+        Some(email) => is_verified(db, discord_user_id, email).await,
+        // Discord destinations need no confirmation
+        None => Ok(true),
+    };
+    let verified = match verified {
+        Ok(verified) => verified,
+        Err(e) => {
+            error!("Error checking email verification: {}", e);
+            edit_response(
+                ctx,
+                command,
+                "Something went wrong. Please try again later.".to_string(),
+            )
+            .await;
+            return;
+        }
+    };
 
-            match products::Entity::insert(product_model).exec(&txn).await {
-                Ok(_) => (),
+    let request = TrackRequest {
+        product_id,
+        email: email.clone(),
+        discord_user_id,
+        guild_id,
+        active: verified,
+        notify_dm,
+        discord_channel_id,
+        webhook_url,
+        time_interval,
+        price_threshold,
+        notify_on_lowest,
+        notify_on_highest,
+        watch_size,
+        discount_threshold,
+        drop_percent,
+        notify_on_restock,
+        periodic_digest,
+    };
+
+    let destinations = describe_destinations(&request);
+    let tracked = track_product(db, request).await;
+    if let Ok(Some(Tracked::Started(_))) = tracked {
+        quotas.start_cooldown(command.user.id);
+    }
+    let response: Response = match (tracked, &email) {
+        // An existing tracker still waiting for its email gets a fresh code too
+        (Ok(Some(Tracked::Started(product) | Tracked::Existing(product))), Some(email))
+            if !verified =>
+        {
+            match start_verification(db, discord_user_id, email).await {
+                Ok(Verification::Sent) => product_embed(&product)
+                    .description(format!(
+                        "📧 Almost done. We sent a code to {}; run `/verifyemail` with it to start receiving alerts.",
+                        email
+                    ))
+                    .into(),
+                Ok(Verification::RecentlySent) => product_embed(&product)
+                    .description(format!(
+                        "📧 Almost done. A code was sent to {} in the last few minutes; run `/verifyemail` with it, or run `/myntra` again later for a new one.",
+                        email
+                    ))
+                    .into(),
                 Err(e) => {
-                    if !e.to_string().contains("duplicate key") {
-                        return Err(e.into());
-                    }
+                    error!("Error sending verification email: {}", e);
+                    "Your tracker was saved, but the verification email could not be sent. Please run `/myntra` again later."
+                        .to_string()
+                        .into()
                 }
             }
+        }
+        (Ok(Some(Tracked::Started(product))), _) => product_embed(&product)
+            .description(format!(
+                "✅ Tracking started. Alerts for this product go to {}.",
+                destinations
+            ))
+            .into(),
+        (Ok(Some(Tracked::Existing(product))), _) => product_embed(&product)
+            .description(
+                "ℹ️ You already track this product. Change its alerts with `/edittracker` or stop them with `/untrack`.",
+            )
+            .into(),
+        (Ok(None), _) => format!(
+            "❌ Myntra has no product with id {}. Check the link and try again.",
+            product_id
+        )
+        .into(),
+        (Err(e), _) => {
+            error!("Error adding myntra tracker: {:?}", e);
+            "Something went wrong while setting up your tracker. Please try again later."
+                .to_string()
+                .into()
+        }
+    };
+    edit_response(ctx, command, response).await;
+}
+
+fn describe_destinations(request: &TrackRequest) -> String {
+    let mut destinations = Vec::new();
+    if let Some(email) = &request.email {
+        destinations.push(email.clone());
+    }
+    if request.notify_dm {
+        destinations.push("your DMs".to_string());
+    }
+    if let Some(channel_id) = request.discord_channel_id {
+        destinations.push(format!("<#{}>", channel_id));
+    }
+    if request.webhook_url.is_some() {
+        destinations.push("your webhook".to_string());
+    }
+    destinations.join(", ")
+}
+
+/// Tracker set up from the "Track this" button on a deals channel post. Alerts go to the
+/// user's DMs, so no email has to be verified; the rules are the `/myntra` defaults.
+/// `Err` carries the reply when a quota or the cooldown refuses the tracker.
+pub async fn track_from_deal(
+    db: &DatabaseConnection,
+    product_id: i32,
+    user_id: UserId,
+    guild_id: Option<i64>,
+    roles: &[RoleId],
+) -> Result<anyhow::Result<Option<Tracked>>, String> {
+    let quotas = QuotaConfig::get();
+    quotas.check_limits(db, user_id, guild_id, roles).await?;
+    quotas.check_cooldown(user_id)?;
+
+    let tracked = track_product(
+        db,
+        TrackRequest {
+            product_id,
+            email: None,
+            discord_user_id: user_id.get() as i64,
+            guild_id,
+            active: true,
+            notify_dm: true,
+            discord_channel_id: None,
+            webhook_url: None,
+            time_interval: DEFAULT_INTERVAL_HOURS.max(quotas.min_interval_hours()),
+            price_threshold: Decimal::new(0, 0),
+            notify_on_lowest: false,
+            notify_on_highest: false,
+            watch_size: None,
+            discount_threshold: None,
+            drop_percent: None,
+            notify_on_restock: false,
+            periodic_digest: true,
+        },
+    )
+    .await;
+    if let Ok(Some(Tracked::Started(_))) = tracked {
+        quotas.start_cooldown(user_id);
+    }
+    Ok(tracked)
+}
+
+// Returns `None` when the product page does not exist
+async fn track_product(
+    db: &DatabaseConnection,
+    request: TrackRequest,
+) -> anyhow::Result<Option<Tracked>> {
+    let product_id = request.product_id;
+    let Some(product) = load_or_scrape_product(db, product_id).await? else {
+        return Ok(None);
+    };
 
-            let notification_preferences = notification_preferences::ActiveModel {
+    // The unique index on (product_id, email) does not cover trackers without an email
+    if request.email.is_none() {
+        let existing = notification_preferences::Entity::find()
+            .filter(notification_preferences::Column::ProductId.eq(product_id))
+            .filter(notification_preferences::Column::DiscordUserId.eq(request.discord_user_id))
+            .filter(notification_preferences::Column::Email.is_null())
+            .count(db)
+            .await?;
+        if existing > 0 {
+            return Ok(Some(Tracked::Existing(product)));
+        }
+    }
+
+    let notification_preferences = notification_preferences::ActiveModel {
+        product_id: Set(product_id),
+        notify_email: Set(request.email.is_some()),
+        email: Set(request.email),
+        discord_user_id: Set(Some(request.discord_user_id)),
+        guild_id: Set(request.guild_id),
+        active: Set(request.active),
+        time_interval_hours: Set(request.time_interval),
+        price_threshold: Set(request.price_threshold),
+        notify_on_lowest: Set(request.notify_on_lowest),
+        notify_on_highest: Set(request.notify_on_highest),
+        last_notified: Set(Utc::now().naive_utc()),
+        created_at: Set(Utc::now().naive_utc()),
+        updated_at: Set(Utc::now().naive_utc()),
+        watch_size: Set(request.watch_size),
+        discount_threshold: Set(request.discount_threshold),
+        drop_percent: Set(request.drop_percent),
+        notify_on_restock: Set(request.notify_on_restock),
+        periodic_digest: Set(request.periodic_digest),
+        notify_dm: Set(request.notify_dm),
+        discord_channel_id: Set(request.discord_channel_id),
+        webhook_url: Set(request.webhook_url),
+        ..Default::default()
+    };
+
+    match notification_preferences::Entity::insert(notification_preferences)
+        .exec(db)
+        .await
+    {
+        Ok(_) => Ok(Some(Tracked::Started(product))),
+        // Re-adding the same product and email keeps the existing tracker
+        Err(e) if e.to_string().contains("duplicate key") => Ok(Some(Tracked::Existing(product))),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// The stored product, or a first scrape of it that is then stored. `None` when Myntra
+/// has no such product.
+pub async fn load_or_scrape_product(
+    db: &DatabaseConnection,
+    product_id: i32,
+) -> anyhow::Result<Option<products::Model>> {
+    let product = match products::Entity::find_by_id(product_id).one(db).await? {
+        Some(product) => product,
+        None => {
+            let snapshot = scrape_products(vec![product_id])
+                .await
+                .map_err(|e| anyhow::anyhow!("Scraping failed: {}", e))?
+                .into_iter()
+                .next()
+                .context("Product page could not be scraped")?;
+            if snapshot.blocked {
+                anyhow::bail!("Myntra blocked the product page request");
+            }
+            if !snapshot.is_product_page() {
+                return Ok(None);
+            }
+
+            let txn = db.begin().await.context("Failed to start transaction")?;
+            let product_model = products::ActiveModel {
                 product_id: Set(product_id),
-                email: Set(email),
-                time_interval_hours: Set(time_interval),
-                price_threshold: Set(price_threshold),
-                notify_on_lowest: Set(notify_on_lowest),
-                notify_on_highest: Set(false),
-                last_notified: Set(Utc::now().naive_utc()),
-                created_at: Set(Utc::now().naive_utc()),
-                updated_at: Set(Utc::now().naive_utc()),
-                ..Default::default()
+                current_price: Set(snapshot.price),
+                highest_price: Set(snapshot.price),
+                lowest_price: Set(snapshot.price),
+                last_updated: Set(Utc::now().naive_utc()),
+                mrp: Set(snapshot.mrp),
+                discount_percent: Set(snapshot.discount_percent),
+                in_stock: Set(snapshot.in_stock),
+                name: Set(snapshot.name.clone()),
+                brand: Set(snapshot.brand.clone()),
+                image_url: Set(snapshot.image_url.clone()),
+                canonical_url: Set(snapshot.canonical_url.clone()),
             };
 
-            match notification_preferences::Entity::insert(notification_preferences)
-                .exec(&txn)
-                .await
-            {
-                Ok(_) => (),
+            match products::Entity::insert(product_model).exec(&txn).await {
+                Ok(_) => update_sizes(&txn, product_id, &snapshot).await?,
                 Err(e) => {
-                    if e.to_string().contains("duplicate key") {
-                        return Ok(());
+                    if !e.to_string().contains("duplicate key") {
+                        return Err(e.into());
                     }
-                    return Err(e.into());
                 }
             }
-
             txn.commit().await.context("Failed to commit transaction")?;
-            Ok(())
-        }
-        .await;
 
-        if let Err(e) = result {
-            error!("Error in background task: {}", e);
+            products::Entity::find_by_id(product_id)
+                .one(db)
+                .await?
+                .context("Product vanished after insert")?
         }
-    });
-
-    Ok("⏳ Your request is being processed. You will receive email notifications once setup is complete.".to_string())
+    };
+    Ok(Some(product))
 }
 
 pub fn register_add() -> CreateCommand {
@@ -132,29 +505,89 @@ pub fn register_add() -> CreateCommand {
         .description("get notifications about the prices of products in Myntra")
         .add_option(
             CreateCommandOption::new(
-                CommandOptionType::Number,
-                "productid",
-                "Enter the ProductId",
+                CommandOptionType::String,
+                "product",
+                "Myntra product link or share link",
             )
             .required(true),
         )
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::String,
+            "email",
+            "Your email address (defaults to the one you verified last)",
+        ))
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Number,
+                "timeintreval",
+                "Time intrevals in hours",
+            )
+            .min_number_value(QuotaConfig::get().min_interval_hours() as f64),
+        )
         .add_option(
-            CreateCommandOption::new(CommandOptionType::String, "email", "Your email address")
-                .required(true),
+            CreateCommandOption::new(
+                CommandOptionType::Number,
+                "pricethreshold",
+                "for custom price alerts",
+            )
+            .min_number_value(0.0),
         )
         .add_option(CreateCommandOption::new(
-            CommandOptionType::Number,
-            "timeintreval",
-            "Time intrevals in hours",
+            CommandOptionType::Boolean,
+            "notifyonlowest",
+            "get notification on lowest price",
         ))
         .add_option(CreateCommandOption::new(
-            CommandOptionType::Number,
-            "pricethreshold",
-            "for custom price alerts",
+            CommandOptionType::String,
+            "size",
+            "get notified when this size is back in stock",
         ))
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Integer,
+                "discountpercent",
+                "get notified when the discount reaches this percentage",
+            )
+            .min_int_value(1)
+            .max_int_value(99),
+        )
         .add_option(CreateCommandOption::new(
             CommandOptionType::Boolean,
-            "notifyonlowest",
-            "get notification on lowest price",
+            "notifyonhighest",
+            "get notification on a new highest price",
+        ))
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Integer,
+                "droppercent",
+                "get notified when the price drops by this percentage",
+            )
+            .min_int_value(1)
+            .max_int_value(99),
+        )
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::Boolean,
+            "notifyonrestock",
+            "get notified when the product is back in stock",
+        ))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::Boolean,
+            "digest",
+            "also send a periodic price summary every timeintreval hours (default true)",
+        ))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::Boolean,
+            "dm",
+            "get alerts as a Discord DM",
+        ))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::Boolean,
+            "postinchannel",
+            "post alerts in this channel (needs Manage Channels)",
+        ))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::String,
+            "webhook",
+            "also POST alerts as JSON to this https URL",
         ))
 }
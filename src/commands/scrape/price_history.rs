@@ -0,0 +1,282 @@
+use crate::cron::history::price_series;
+use crate::scraper::myntra::resolve_product_id;
+use crate::utils::analytics::PriceAnalytics;
+use crate::utils::charts::{price_chart, CHART_FILE_NAME};
+use crate::utils::embeds::{product_embed, with_analytics};
+use crate::utils::export::{parse_date, ExportFormat, PriceExport};
+use crate::utils::util::{create_response, edit_response, product_display_name};
+use ::entity::{notification_preferences, products};
+use chrono::{NaiveDate, Utc};
+use sea_orm::*;
+use serenity::all::{
+    CommandInteraction, CommandOptionType, Context, CreateAttachment, CreateCommand,
+    CreateCommandOption, EditInteractionResponse, ResolvedOption, ResolvedValue,
+};
+use tracing::error;
+
+const DEFAULT_DAYS: i64 = 90;
+const MAX_DAYS: i64 = 730;
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("pricehistory")
+        .description("Chart or export the price history of a tracked Myntra product")
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "view",
+                "Show a price chart with trend figures",
+            )
+            .add_sub_option(product_option())
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::Integer,
+                    "days",
+                    "How many days of history to show (default 90)",
+                )
+                .min_int_value(1)
+                .max_int_value(MAX_DAYS as u64),
+            ),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "export",
+                "Download the price history as a CSV or JSON file",
+            )
+            .add_sub_option(product_option())
+            .add_sub_option(CreateCommandOption::new(
+                CommandOptionType::String,
+                "from",
+                "First day to include, like 2025-01-31",
+            ))
+            .add_sub_option(CreateCommandOption::new(
+                CommandOptionType::String,
+                "to",
+                "Last day to include, like 2025-03-31",
+            ))
+            .add_sub_option(
+                CreateCommandOption::new(CommandOptionType::String, "format", "File format")
+                    .add_string_choice("CSV", "csv")
+                    .add_string_choice("JSON", "json"),
+            ),
+        )
+}
+
+fn product_option() -> CreateCommandOption {
+    CreateCommandOption::new(
+        CommandOptionType::String,
+        "product",
+        "Myntra product link, share link or id",
+    )
+    .required(true)
+}
+
+pub async fn run(ctx: &Context, command: &CommandInteraction, db: &DatabaseConnection) {
+    let options = command.data.options();
+    let Some(ResolvedOption {
+        name,
+        value: ResolvedValue::SubCommand(sub_options),
+        ..
+    }) = options.first()
+    else {
+        create_response(ctx, command, "Please choose a subcommand".to_string()).await;
+        return;
+    };
+
+    let mut product = None;
+    let mut days = DEFAULT_DAYS;
+    let mut from = None;
+    let mut to = None;
+    let mut format = ExportFormat::Csv;
+    for option in sub_options {
+        match (option.name, &option.value) {
+            ("product", ResolvedValue::String(link)) => product = Some((*link).to_string()),
+            ("days", ResolvedValue::Integer(value)) => days = (*value).clamp(1, MAX_DAYS),
+            ("from", ResolvedValue::String(date)) => match parse_date(date) {
+                Ok(date) => from = Some(date),
+                Err(message) => return create_response(ctx, command, message).await,
+            },
+            ("to", ResolvedValue::String(date)) => match parse_date(date) {
+                Ok(date) => to = Some(date),
+                Err(message) => return create_response(ctx, command, message).await,
+            },
+            ("format", ResolvedValue::String(value)) => {
+                format = ExportFormat::parse(value).unwrap_or(ExportFormat::Csv)
+            }
+            _ => {}
+        }
+    }
+
+    let Some(product) = product else {
+        create_response(
+            ctx,
+            command,
+            "Please provide a Myntra product link".to_string(),
+        )
+        .await;
+        return;
+    };
+    if from.zip(to).is_some_and(|(from, to)| from > to) {
+        create_response(
+            ctx,
+            command,
+            "The `from` date must not be after the `to` date".to_string(),
+        )
+        .await;
+        return;
+    }
+
+    // Share links need a round trip and the chart takes a moment to draw
+    if let Err(why) = command.defer(&ctx.http).await {
+        error!("Cannot defer pricehistory command: {why}");
+        return;
+    }
+
+    let product_id = match resolve_product_id(&product).await {
+        Ok(id) => id,
+        Err(message) => {
+            edit_response(ctx, command, message).await;
+            return;
+        }
+    };
+
+    match *name {
+        "export" => export(ctx, command, db, product_id, from, to, format).await,
+        _ => view(ctx, command, db, product_id, days).await,
+    }
+}
+
+async fn view(
+    ctx: &Context,
+    command: &CommandInteraction,
+    db: &DatabaseConnection,
+    product_id: i32,
+    days: i64,
+) {
+    match chart_for(db, product_id, command.user.id.get() as i64, days).await {
+        Ok(Some((product, png, analytics))) => {
+            let mut embed =
+                product_embed(&product).image(format!("attachment://{}", CHART_FILE_NAME));
+            if let Some(analytics) = &analytics {
+                embed = with_analytics(embed, analytics);
+            }
+            let builder = EditInteractionResponse::new()
+                .embed(embed)
+                .new_attachment(CreateAttachment::bytes(png, CHART_FILE_NAME));
+            if let Err(why) = command.edit_response(&ctx.http, builder).await {
+                error!("Cannot send price chart: {why}");
+            }
+        }
+        Ok(None) => {
+            edit_response(
+                ctx,
+                command,
+                "This product is not tracked yet. Use `/myntra` to start tracking it.".to_string(),
+            )
+            .await
+        }
+        Err(message) => edit_response(ctx, command, message).await,
+    }
+}
+
+// The caller's own price threshold, if they track the product, is drawn on the chart.
+// The trend figures cover the same range as the chart.
+async fn chart_for(
+    db: &DatabaseConnection,
+    product_id: i32,
+    discord_user_id: i64,
+    days: i64,
+) -> Result<Option<(products::Model, Vec<u8>, Option<PriceAnalytics>)>, String> {
+    let internal_error = |e: DbErr| {
+        error!("Failed to load price history for {}: {}", product_id, e);
+        "Could not load the price history. Please try again later.".to_string()
+    };
+
+    let Some(product) = products::Entity::find_by_id(product_id)
+        .one(db)
+        .await
+        .map_err(internal_error)?
+    else {
+        return Ok(None);
+    };
+
+    let since = Utc::now().naive_utc() - chrono::Duration::days(days);
+    let history = price_series(db, product_id, since)
+        .await
+        .map_err(internal_error)?;
+
+    let threshold = notification_preferences::Entity::find()
+        .filter(notification_preferences::Column::ProductId.eq(product_id))
+        .filter(notification_preferences::Column::DiscordUserId.eq(discord_user_id))
+        .one(db)
+        .await
+        .map_err(internal_error)?
+        .map(|preference| preference.price_threshold);
+
+    let png = price_chart(&history, threshold)?;
+    let analytics = PriceAnalytics::compute(&history, product.current_price);
+    Ok(Some((product, png, analytics)))
+}
+
+async fn export(
+    ctx: &Context,
+    command: &CommandInteraction,
+    db: &DatabaseConnection,
+    product_id: i32,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    format: ExportFormat,
+) {
+    let export = match PriceExport::load(db, product_id, from, to).await {
+        Ok(Some(export)) => export,
+        Ok(None) => {
+            edit_response(
+                ctx,
+                command,
+                "This product is not tracked yet. Use `/myntra` to start tracking it.".to_string(),
+            )
+            .await;
+            return;
+        }
+        Err(e) => {
+            error!("Failed to export price history for {}: {}", product_id, e);
+            edit_response(
+                ctx,
+                command,
+                "Could not load the price history. Please try again later.".to_string(),
+            )
+            .await;
+            return;
+        }
+    };
+
+    if export.rows.is_empty() {
+        edit_response(
+            ctx,
+            command,
+            "No prices were recorded in that range".to_string(),
+        )
+        .await;
+        return;
+    }
+
+    let file = match export.render(format) {
+        Ok(file) => file,
+        Err(e) => {
+            error!("Failed to render price export for {}: {}", product_id, e);
+            edit_response(ctx, command, "Could not build the export".to_string()).await;
+            return;
+        }
+    };
+
+    let builder = EditInteractionResponse::new()
+        .content(format!(
+            "📄 {} prices for {}",
+            export.rows.len(),
+            product_display_name(&export.product)
+        ))
+        .new_attachment(CreateAttachment::bytes(file, export.file_name(format)));
+    if let Err(why) = command.edit_response(&ctx.http, builder).await {
+        error!("Cannot send price export: {why}");
+    }
+}
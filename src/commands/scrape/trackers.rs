@@ -0,0 +1,339 @@
+use crate::config::quotas::QuotaConfig;
+use crate::utils::util::{product_display_name, Response};
+use ::entity::{notification_preferences, products};
+use sea_orm::prelude::Decimal;
+use sea_orm::sqlx::types::chrono::Utc;
+use sea_orm::*;
+use serenity::all::{
+    AutocompleteChoice, Color, CommandInteraction, CommandOptionType, Context,
+    CreateAutocompleteResponse, CreateCommand, CreateCommandOption, CreateEmbed,
+    CreateInteractionResponse, ResolvedValue,
+};
+use tracing::error;
+
+// Discord allows at most 25 embed fields and 25 autocomplete choices
+const MAX_SHOWN: usize = 25;
+
+type Tracker = (notification_preferences::Model, Option<products::Model>);
+
+pub fn register_list() -> CreateCommand {
+    CreateCommand::new("mytrackers").description("List the products you are tracking")
+}
+
+pub fn register_untrack() -> CreateCommand {
+    CreateCommand::new("untrack")
+        .description("Stop tracking a product")
+        .add_option(tracker_option())
+}
+
+pub fn register_edit() -> CreateCommand {
+    CreateCommand::new("edittracker")
+        .description("Change the alert settings of a tracked product")
+        .add_option(tracker_option())
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Number,
+                "timeintreval",
+                "Time intrevals in hours",
+            )
+            .min_number_value(QuotaConfig::get().min_interval_hours() as f64),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::Number,
+                "pricethreshold",
+                "for custom price alerts, 0 to turn off",
+            )
+            .min_number_value(0.0),
+        )
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::Boolean,
+            "notifyonlowest",
+            "get notification on lowest price",
+        ))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::Boolean,
+            "notifyonhighest",
+            "get notification on a new highest price",
+        ))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::Boolean,
+            "notifyonrestock",
+            "get notified when the product is back in stock",
+        ))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::Boolean,
+            "digest",
+            "send a periodic price summary every timeintreval hours",
+        ))
+}
+
+fn tracker_option() -> CreateCommandOption {
+    CreateCommandOption::new(CommandOptionType::Integer, "tracker", "The tracked product")
+        .required(true)
+        .set_autocomplete(true)
+}
+
+pub async fn list(command: &CommandInteraction, db: &DatabaseConnection) -> Response {
+    let trackers = match user_trackers(db, command.user.id.get() as i64).await {
+        Ok(trackers) => trackers,
+        Err(e) => {
+            error!("Failed to load trackers: {}", e);
+            return "Could not load your trackers. Please try again later."
+                .to_string()
+                .into();
+        }
+    };
+
+    if trackers.is_empty() {
+        return "You are not tracking anything yet. Use `/myntra` to start."
+            .to_string()
+            .into();
+    }
+
+    let mut embed = CreateEmbed::default()
+        .title("Your trackers")
+        .color(Color::BLUE);
+    for (preference, product) in trackers.iter().take(MAX_SHOWN) {
+        embed = embed.field(
+            tracker_name(preference, product),
+            summary(preference, product),
+            false,
+        );
+    }
+    if trackers.len() > MAX_SHOWN {
+        embed = embed.description(format!(
+            "Showing {} of {} trackers",
+            MAX_SHOWN,
+            trackers.len()
+        ));
+    }
+    embed.into()
+}
+
+pub async fn untrack(command: &CommandInteraction, db: &DatabaseConnection) -> String {
+    let Some(preference_id) = tracker_id(command) else {
+        return "Please choose a tracker".to_string();
+    };
+
+    match owned_tracker(db, command.user.id.get() as i64, preference_id).await {
+        Ok(Some((preference, product))) => {
+            let name = tracker_name(&preference, &product);
+            match preference.delete(db).await {
+                Ok(_) => format!("🗑️ Stopped tracking {}", name),
+                Err(e) => {
+                    error!("Failed to delete tracker {}: {}", preference_id, e);
+                    "Could not remove that tracker. Please try again later.".to_string()
+                }
+            }
+        }
+        Ok(None) => "You have no tracker with that id".to_string(),
+        Err(e) => {
+            error!("Failed to load tracker {}: {}", preference_id, e);
+            "Could not remove that tracker. Please try again later.".to_string()
+        }
+    }
+}
+
+pub async fn edit(command: &CommandInteraction, db: &DatabaseConnection) -> String {
+    let Some(preference_id) = tracker_id(command) else {
+        return "Please choose a tracker".to_string();
+    };
+
+    let (preference, product) =
+        match owned_tracker(db, command.user.id.get() as i64, preference_id).await {
+            Ok(Some(tracker)) => tracker,
+            Ok(None) => return "You have no tracker with that id".to_string(),
+            Err(e) => {
+                error!("Failed to load tracker {}: {}", preference_id, e);
+                return "Could not update that tracker. Please try again later.".to_string();
+            }
+        };
+
+    let name = tracker_name(&preference, &product);
+    let mut update: notification_preferences::ActiveModel = preference.into();
+    let mut changed = false;
+
+    for option in command.data.options() {
+        match (option.name, &option.value) {
+            ("timeintreval", ResolvedValue::Number(hours)) => {
+                let hours = *hours as i32;
+                if let Err(message) = QuotaConfig::get().check_interval(hours) {
+                    return message;
+                }
+                update.time_interval_hours = Set(hours);
+            }
+            ("pricethreshold", ResolvedValue::Number(threshold)) => {
+                match Decimal::try_from(*threshold) {
+                    Ok(threshold) => update.price_threshold = Set(threshold),
+                    Err(_) => return "Invalid price threshold".to_string(),
+                }
+            }
+            ("notifyonlowest", ResolvedValue::Boolean(notify)) => {
+                update.notify_on_lowest = Set(*notify)
+            }
+            ("notifyonhighest", ResolvedValue::Boolean(notify)) => {
+                update.notify_on_highest = Set(*notify)
+            }
+            ("notifyonrestock", ResolvedValue::Boolean(notify)) => {
+                update.notify_on_restock = Set(*notify)
+            }
+            ("digest", ResolvedValue::Boolean(digest)) => update.periodic_digest = Set(*digest),
+            _ => continue,
+        }
+        changed = true;
+    }
+
+    if !changed {
+        return "Nothing to change. Pick at least one setting to update.".to_string();
+    }
+    update.updated_at = Set(Utc::now().naive_utc());
+
+    match update.update(db).await {
+        Ok(preference) => format!("✏️ Updated {}\n{}", name, summary(&preference, &product)),
+        Err(e) => {
+            error!("Failed to update tracker {}: {}", preference_id, e);
+            "Could not update that tracker. Please try again later.".to_string()
+        }
+    }
+}
+
+/// Suggests the user's own trackers for the `tracker` option, matching on product name.
+pub async fn autocomplete(
+    ctx: &Context,
+    interaction: &CommandInteraction,
+    db: &DatabaseConnection,
+) {
+    let typed = interaction
+        .data
+        .autocomplete()
+        .map(|option| option.value.to_lowercase())
+        .unwrap_or_default();
+
+    let trackers = match user_trackers(db, interaction.user.id.get() as i64).await {
+        Ok(trackers) => trackers,
+        Err(e) => {
+            error!("Failed to load trackers for autocomplete: {}", e);
+            Vec::new()
+        }
+    };
+
+    let choices = trackers
+        .iter()
+        .map(|(preference, product)| (preference.preference_id, tracker_name(preference, product)))
+        .filter(|(_, name)| name.to_lowercase().contains(&typed))
+        .take(MAX_SHOWN)
+        .map(|(id, name)| {
+            // Choice names are capped at 100 characters
+            let name: String = name.chars().take(100).collect();
+            AutocompleteChoice::new(name, id)
+        })
+        .collect();
+
+    let response = CreateInteractionResponse::Autocomplete(
+        CreateAutocompleteResponse::new().set_choices(choices),
+    );
+    if let Err(why) = interaction.create_response(&ctx.http, response).await {
+        error!("Cannot respond to autocomplete: {why}");
+    }
+}
+
+async fn user_trackers(
+    db: &DatabaseConnection,
+    discord_user_id: i64,
+) -> Result<Vec<Tracker>, DbErr> {
+    notification_preferences::Entity::find()
+        .filter(notification_preferences::Column::DiscordUserId.eq(discord_user_id))
+        .order_by(notification_preferences::Column::CreatedAt, Order::Asc)
+        .find_also_related(products::Entity)
+        .all(db)
+        .await
+}
+
+// Only the user who created a tracker may change it
+async fn owned_tracker(
+    db: &DatabaseConnection,
+    discord_user_id: i64,
+    preference_id: i32,
+) -> Result<Option<Tracker>, DbErr> {
+    notification_preferences::Entity::find_by_id(preference_id)
+        .filter(notification_preferences::Column::DiscordUserId.eq(discord_user_id))
+        .find_also_related(products::Entity)
+        .one(db)
+        .await
+}
+
+fn tracker_id(command: &CommandInteraction) -> Option<i32> {
+    command
+        .data
+        .options()
+        .into_iter()
+        .find_map(|option| match (option.name, option.value) {
+            ("tracker", ResolvedValue::Integer(id)) => Some(id as i32),
+            _ => None,
+        })
+}
+
+fn tracker_name(
+    preference: &notification_preferences::Model,
+    product: &Option<products::Model>,
+) -> String {
+    match product {
+        Some(product) => format!(
+            "#{} {}",
+            preference.preference_id,
+            product_display_name(product)
+        ),
+        None => format!(
+            "#{} Myntra product {}",
+            preference.preference_id, preference.product_id
+        ),
+    }
+}
+
+fn summary(
+    preference: &notification_preferences::Model,
+    product: &Option<products::Model>,
+) -> String {
+    let mut parts = Vec::new();
+    if let Some(product) = product {
+        parts.push(format!("₹{}", product.current_price));
+    }
+    parts.push(format!("every {}h", preference.time_interval_hours));
+    if !preference.price_threshold.is_zero() {
+        parts.push(format!("below ₹{}", preference.price_threshold));
+    }
+    if let Some(discount) = preference.discount_threshold {
+        parts.push(format!("{}% off", discount));
+    }
+    if let Some(drop) = preference.drop_percent {
+        parts.push(format!("drops of {}%", drop));
+    }
+    if let Some(size) = &preference.watch_size {
+        parts.push(format!("size {}", size));
+    }
+    for (enabled, flag) in [
+        (preference.notify_on_lowest, "new lows"),
+        (preference.notify_on_highest, "new highs"),
+        (preference.notify_on_restock, "restocks"),
+        (preference.periodic_digest, "digest"),
+    ] {
+        if enabled {
+            parts.push(flag.to_string());
+        }
+    }
+
+    let status = if !preference.active {
+        " (waiting for email verification)"
+    } else if preference.paused {
+        " (paused)"
+    } else if preference
+        .snoozed_until
+        .is_some_and(|until| until > Utc::now().naive_utc())
+    {
+        " (snoozed)"
+    } else {
+        ""
+    };
+    format!("{}{}", parts.join(" · "), status)
+}
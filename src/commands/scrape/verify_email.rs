@@ -0,0 +1,42 @@
+use crate::utils::emails::verify_email::confirm_code;
+use sea_orm::DatabaseConnection;
+use serenity::all::{
+    CommandInteraction, CommandOptionType, CreateCommand, CreateCommandOption, ResolvedOption,
+    ResolvedValue,
+};
+use tracing::error;
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("verifyemail")
+        .description("Confirm the email address used for price alerts")
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "code",
+                "The 6 digit code from the confirmation email",
+            )
+            .required(true),
+        )
+}
+
+pub async fn run(command: &CommandInteraction, db: &DatabaseConnection) -> String {
+    let code = match command.data.options().first() {
+        Some(ResolvedOption {
+            value: ResolvedValue::String(code),
+            ..
+        }) => code.to_string(),
+        _ => return "Please provide the code from the confirmation email".to_string(),
+    };
+
+    match confirm_code(db, command.user.id.get() as i64, &code).await {
+        Ok(Some(email)) => format!(
+            "✅ {} is verified. Your price alerts for it are now active, and `/myntra` will reuse it when you leave out the email.",
+            email
+        ),
+        Ok(None) => "❌ That code is invalid or has expired. After 5 wrong codes you need a new one; run `/myntra` again to get it.".to_string(),
+        Err(e) => {
+            error!("Failed to verify email: {}", e);
+            "Something went wrong while verifying your email. Please try again later.".to_string()
+        }
+    }
+}
@@ -0,0 +1,278 @@
+use crate::commands::scrape::myntra::load_or_scrape_product;
+use crate::config::quotas::QuotaConfig;
+use crate::scraper::myntra::resolve_product_id;
+use crate::utils::embeds::product_embed;
+use crate::utils::util::{create_response, edit_response, product_display_name, Response};
+use ::entity::{channel_watchlists, products};
+use chrono::Utc;
+use sea_orm::*;
+use serenity::all::{
+    Color, CommandInteraction, CommandOptionType, Context, CreateCommand, CreateCommandOption,
+    CreateEmbed, ResolvedOption, ResolvedValue,
+};
+use tracing::error;
+
+// Every watched product is scraped hourly, so one channel cannot watch the whole store
+const MAX_WATCHED: u64 = 50;
+// Discord allows at most 25 embed fields
+const MAX_SHOWN: usize = 25;
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("watchlist")
+        .description("Watch Myntra products together in this channel")
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "add",
+                "Add a product to this channel's watchlist",
+            )
+            .add_sub_option(product_option()),
+        )
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "remove",
+                "Remove a product from this channel's watchlist",
+            )
+            .add_sub_option(product_option()),
+        )
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "list",
+            "Show the products this channel watches",
+        ))
+}
+
+fn product_option() -> CreateCommandOption {
+    CreateCommandOption::new(
+        CommandOptionType::String,
+        "product",
+        "Myntra product link or id",
+    )
+    .required(true)
+}
+
+pub async fn run(ctx: &Context, command: &CommandInteraction, db: &DatabaseConnection) {
+    let Some(guild_id) = command.guild_id else {
+        create_response(
+            ctx,
+            command,
+            "Watchlists can only be used in a server".to_string(),
+        )
+        .await;
+        return;
+    };
+    let options = command.data.options();
+    let Some(ResolvedOption {
+        name,
+        value: ResolvedValue::SubCommand(sub_options),
+        ..
+    }) = options.first()
+    else {
+        create_response(ctx, command, "Please choose a subcommand".to_string()).await;
+        return;
+    };
+    let product = sub_options.iter().find_map(|option| match option.value {
+        ResolvedValue::String(product) if option.name == "product" => Some(product),
+        _ => None,
+    });
+    let channel_id = command.channel_id.get() as i64;
+
+    match (*name, product) {
+        ("list", _) => create_response(ctx, command, list(db, channel_id).await).await,
+        ("add", Some(product)) => {
+            let product_id = match resolve_product_id(product).await {
+                Ok(id) => id,
+                Err(message) => return create_response(ctx, command, message).await,
+            };
+            let quotas = QuotaConfig::get();
+            let roles = command
+                .member
+                .as_ref()
+                .map(|member| member.roles.clone())
+                .unwrap_or_default();
+            let allowed = match quotas
+                .check_limits(db, command.user.id, Some(guild_id.get() as i64), &roles)
+                .await
+            {
+                Ok(()) => quotas.check_cooldown(command.user.id),
+                Err(message) => Err(message),
+            };
+            if let Err(message) = allowed {
+                return create_response(ctx, command, message).await;
+            }
+            // A product nobody tracks yet is scraped first, longer than Discord waits
+            if let Err(why) = command.defer(&ctx.http).await {
+                error!("Cannot defer watchlist command: {why}");
+                return;
+            }
+            let response = add(db, command, guild_id.get() as i64, product_id).await;
+            edit_response(ctx, command, response).await
+        }
+        ("remove", Some(product)) => {
+            let response = match resolve_product_id(product).await {
+                Ok(product_id) => remove(db, channel_id, product_id).await,
+                Err(message) => message,
+            };
+            create_response(ctx, command, response).await
+        }
+        ("add" | "remove", None) => {
+            create_response(ctx, command, "Please provide a product".to_string()).await
+        }
+        _ => create_response(ctx, command, "not implemented :(".to_string()).await,
+    }
+}
+
+async fn add(
+    db: &DatabaseConnection,
+    command: &CommandInteraction,
+    guild_id: i64,
+    product_id: i32,
+) -> Response {
+    let channel_id = command.channel_id.get() as i64;
+    let failed = |e: &dyn std::fmt::Display| -> Response {
+        error!(
+            "Failed to add {} to watchlist {}: {}",
+            product_id, channel_id, e
+        );
+        "Could not update the watchlist. Please try again later."
+            .to_string()
+            .into()
+    };
+
+    let watched = channel_watchlists::Entity::find()
+        .filter(channel_watchlists::Column::ChannelId.eq(channel_id))
+        .all(db)
+        .await;
+    let watched = match watched {
+        Ok(watched) => watched,
+        Err(e) => return failed(&e),
+    };
+    if watched.iter().any(|watch| watch.product_id == product_id) {
+        return format!(
+            "Product {} is already on this channel's watchlist.",
+            product_id
+        )
+        .into();
+    }
+    if watched.len() as u64 >= MAX_WATCHED {
+        return format!(
+            "🚫 This channel already watches {} products, the most it can. Remove one with `/watchlist remove` first.",
+            MAX_WATCHED
+        )
+        .into();
+    }
+
+    let product = match load_or_scrape_product(db, product_id).await {
+        Ok(Some(product)) => product,
+        Ok(None) => {
+            return format!(
+                "❌ Myntra has no product with id {}. Check the link and try again.",
+                product_id
+            )
+            .into()
+        }
+        Err(e) => return failed(&e),
+    };
+
+    let watch = channel_watchlists::ActiveModel {
+        channel_id: Set(channel_id),
+        guild_id: Set(guild_id),
+        product_id: Set(product_id),
+        added_by: Set(command.user.id.get() as i64),
+        created_at: Set(Utc::now().naive_utc()),
+        ..Default::default()
+    };
+    match watch.insert(db).await {
+        Ok(_) => QuotaConfig::get().start_cooldown(command.user.id),
+        // Someone added the same product a moment earlier
+        Err(e) if e.to_string().contains("duplicate key") => {}
+        Err(e) => return failed(&e),
+    }
+
+    product_embed(&product)
+        .description(format!(
+            "👀 Added to the watchlist of <#{}>. Price drops, all-time lows and restocks are posted here.",
+            channel_id
+        ))
+        .into()
+}
+
+async fn remove(db: &DatabaseConnection, channel_id: i64, product_id: i32) -> String {
+    let removed = channel_watchlists::Entity::delete_many()
+        .filter(channel_watchlists::Column::ChannelId.eq(channel_id))
+        .filter(channel_watchlists::Column::ProductId.eq(product_id))
+        .exec(db)
+        .await;
+    match removed {
+        Ok(result) if result.rows_affected > 0 => {
+            format!("🗑️ Product {} is no longer watched here.", product_id)
+        }
+        Ok(_) => format!("Product {} is not on this channel's watchlist.", product_id),
+        Err(e) => {
+            error!(
+                "Failed to remove {} from watchlist {}: {}",
+                product_id, channel_id, e
+            );
+            "Could not update the watchlist. Please try again later.".to_string()
+        }
+    }
+}
+
+async fn list(db: &DatabaseConnection, channel_id: i64) -> Response {
+    let watched = channel_watchlists::Entity::find()
+        .filter(channel_watchlists::Column::ChannelId.eq(channel_id))
+        .order_by_asc(channel_watchlists::Column::CreatedAt)
+        .find_also_related(products::Entity)
+        .all(db)
+        .await;
+    let watched = match watched {
+        Ok(watched) => watched,
+        Err(e) => {
+            error!("Failed to load watchlist {}: {}", channel_id, e);
+            return "Could not load the watchlist. Please try again later."
+                .to_string()
+                .into();
+        }
+    };
+
+    if watched.is_empty() {
+        return "This channel watches nothing yet. Add a product with `/watchlist add`."
+            .to_string()
+            .into();
+    }
+
+    let mut embed = CreateEmbed::new()
+        .title("Channel watchlist")
+        .color(Color::TEAL);
+    for (watch, product) in watched.iter().take(MAX_SHOWN) {
+        let (name, price) = match product {
+            Some(product) => (
+                product_display_name(product),
+                format!(
+                    "₹{} (lowest ₹{}){}",
+                    product.current_price,
+                    product.lowest_price,
+                    if product.in_stock { "" } else { ", sold out" }
+                ),
+            ),
+            None => (
+                format!("Myntra product #{}", watch.product_id),
+                "Not scraped yet".to_string(),
+            ),
+        };
+        embed = embed.field(
+            name,
+            format!("{}\nAdded by <@{}>", price, watch.added_by),
+            false,
+        );
+    }
+    if watched.len() > MAX_SHOWN {
+        embed = embed.description(format!(
+            "Showing {} of {} products",
+            MAX_SHOWN,
+            watched.len()
+        ));
+    }
+    embed.into()
+}
@@ -0,0 +1,260 @@
+use crate::scraper::browser::BrowserPool;
+use crate::scraper::myntra::resolve_product_id;
+use crate::scraper::price_scraper::PriceScraper;
+use crate::scraper::status::{RunReport, ScraperStatus, StatusSnapshot};
+use crate::utils::embeds::product_embed;
+use crate::utils::util::{create_response, edit_response, Response};
+use ::entity::products;
+use chrono::{DateTime, Utc};
+use sea_orm::*;
+use serenity::all::{
+    Color, CommandInteraction, CommandOptionType, Context, CreateCommand, CreateCommandOption,
+    CreateEmbed, Permissions, ResolvedOption, ResolvedValue,
+};
+use tracing::error;
+
+const BROKEN_SHOWN: usize = 10;
+
+pub fn register() -> CreateCommand {
+    CreateCommand::new("scraper")
+        .description("Inspect and control the hourly price scraper")
+        .default_member_permissions(Permissions::ADMINISTRATOR)
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "status",
+            "Show the last run, failures and broken products",
+        ))
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "scrape",
+                "Scrape one tracked product now",
+            )
+            .add_sub_option(
+                CreateCommandOption::new(
+                    CommandOptionType::String,
+                    "product",
+                    "Myntra product link or id",
+                )
+                .required(true),
+            ),
+        )
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "pause",
+            "Skip the hourly runs until resumed",
+        ))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::SubCommand,
+            "resume",
+            "Start the hourly runs again",
+        ))
+}
+
+pub async fn run(
+    ctx: &Context,
+    command: &CommandInteraction,
+    db: &DatabaseConnection,
+    scraper: &PriceScraper,
+) {
+    let options = command.data.options();
+    let Some(ResolvedOption {
+        name,
+        value: ResolvedValue::SubCommand(sub_options),
+        ..
+    }) = options.first()
+    else {
+        create_response(ctx, command, "Please choose a subcommand".to_string()).await;
+        return;
+    };
+
+    let status = ScraperStatus::get();
+    match *name {
+        "status" => create_response(ctx, command, status_embed(&status.snapshot())).await,
+        "pause" => {
+            let message = if status.set_paused(true) {
+                "The scraper is already paused."
+            } else {
+                "⏸️ Scraper paused. A run in progress stops after the current product."
+            };
+            create_response(ctx, command, message.to_string()).await
+        }
+        "resume" => {
+            let message = if status.set_paused(false) {
+                "▶️ Scraper resumed. The next hourly run goes ahead as usual."
+            } else {
+                "The scraper is not paused."
+            };
+            create_response(ctx, command, message.to_string()).await
+        }
+        "scrape" => {
+            let Some(ResolvedOption {
+                value: ResolvedValue::String(product),
+                ..
+            }) = sub_options.first()
+            else {
+                create_response(ctx, command, "Please provide a product".to_string()).await;
+                return;
+            };
+            // A scrape takes a few seconds, longer than Discord waits for the first reply
+            if let Err(why) = command.defer(&ctx.http).await {
+                error!("Cannot defer scraper command: {why}");
+                return;
+            }
+            let response = scrape_now(db, scraper, product).await;
+            edit_response(ctx, command, response).await
+        }
+        _ => create_response(ctx, command, "not implemented :(".to_string()).await,
+    }
+}
+
+async fn scrape_now(db: &DatabaseConnection, scraper: &PriceScraper, product: &str) -> Response {
+    let product_id = match resolve_product_id(product).await {
+        Ok(id) => id,
+        Err(message) => return message.into(),
+    };
+    match products::Entity::find_by_id(product_id).one(db).await {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            return format!(
+                "Product {} is not tracked. Add it with `/myntra` first.",
+                product_id
+            )
+            .into()
+        }
+        Err(e) => {
+            error!("Failed to load product {}: {}", product_id, e);
+            return "Something went wrong. Please try again later."
+                .to_string()
+                .into();
+        }
+    }
+
+    match scraper.scrape_product(product_id).await {
+        Ok(product) => product_embed(&product)
+            .description("✅ Scraped just now")
+            .into(),
+        Err(failure) => format!(
+            "❌ Product {} could not be scraped: {}",
+            product_id, failure
+        )
+        .into(),
+    }
+}
+
+fn status_embed(status: &StatusSnapshot) -> CreateEmbed {
+    let state = match (status.paused, status.running_since) {
+        (true, Some(_)) => "⏸️ Pausing after the current product".to_string(),
+        (true, None) => "⏸️ Paused".to_string(),
+        (false, Some(since)) => format!("🔄 Running since {}", relative(since)),
+        (false, None) => "✅ Idle".to_string(),
+    };
+    let mut embed = CreateEmbed::new()
+        .title("Price scraper")
+        .color(if status.paused {
+            Color::ORANGE
+        } else if status.broken.is_empty() {
+            Color::DARK_GREEN
+        } else {
+            Color::RED
+        })
+        .field("State", state, true);
+
+    if let Some(next_run) = status.next_run.filter(|_| !status.paused) {
+        embed = embed.field("Next run", relative(next_run), true);
+    }
+
+    match &status.last_run {
+        Some(report) => {
+            embed = embed
+                .field(
+                    "Last run",
+                    format!(
+                        "{}, took {}m {}s{}",
+                        relative(report.started_at),
+                        report.duration.as_secs() / 60,
+                        report.duration.as_secs() % 60,
+                        if report.interrupted {
+                            " (stopped by pause)"
+                        } else {
+                            ""
+                        }
+                    ),
+                    false,
+                )
+                .field(
+                    "Products",
+                    format!("{} of {} updated", report.updated, report.products),
+                    true,
+                )
+                .field("Failures", failures(report), true);
+        }
+        None => embed = embed.field("Last run", "No run since the bot started", false),
+    }
+
+    let pool = BrowserPool::get().metrics();
+    embed = embed.field(
+        "Browsers",
+        format!(
+            "{}/{} running, {}/{} tabs, {} restarts, {} blocked pages",
+            pool.browsers_running,
+            pool.browsers,
+            pool.tabs_in_use,
+            pool.max_tabs,
+            pool.restarts,
+            pool.blocked_pages
+        ),
+        false,
+    );
+
+    if !status.broken.is_empty() {
+        let mut lines: Vec<String> = status
+            .broken
+            .iter()
+            .take(BROKEN_SHOWN)
+            .map(|broken| {
+                let mut failure = broken.failure.to_string();
+                if failure.chars().count() > 80 {
+                    failure = failure.chars().take(79).collect::<String>() + "…";
+                }
+                format!(
+                    "`{}` {} ({} in a row, since {})",
+                    broken.product_id,
+                    failure,
+                    broken.failures_in_row,
+                    relative(broken.since)
+                )
+            })
+            .collect();
+        if status.broken.len() > BROKEN_SHOWN {
+            lines.push(format!("…and {} more", status.broken.len() - BROKEN_SHOWN));
+        }
+        embed = embed.field("Broken products", lines.join("\n"), false);
+    }
+
+    embed
+}
+
+fn failures(report: &RunReport) -> String {
+    let counts = [
+        ("blocked", report.blocked),
+        ("did not load", report.fetch_errors),
+        ("not a product page", report.not_product_pages),
+        ("not saved", report.save_errors),
+    ];
+    let failures: Vec<String> = counts
+        .iter()
+        .filter(|(_, count)| *count > 0)
+        .map(|(label, count)| format!("{} {}", count, label))
+        .collect();
+    if failures.is_empty() {
+        "None".to_string()
+    } else {
+        failures.join(", ")
+    }
+}
+
+// Discord renders this in the reader's timezone, e.g. "5 minutes ago"
+fn relative(time: DateTime<Utc>) -> String {
+    format!("<t:{}:R>", time.timestamp())
+}
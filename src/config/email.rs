@@ -1,63 +1,181 @@
 use lettre::{
-    transport::smtp::authentication::Credentials, AsyncSmtpTransport, AsyncTransport, Message,
-    Tokio1Executor,
+    message::{
+        header::{ContentType, HeaderName, HeaderValue},
+        Attachment, Mailbox, MultiPart, SinglePart,
+    },
+    transport::{
+        smtp::{authentication::Credentials, PoolConfig},
+        stub::AsyncStubTransport,
+    },
+    AsyncFileTransport, AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
 };
 use once_cell::sync::OnceCell;
 use shuttle_runtime::SecretStore;
+use std::time::Duration;
 
 static EMAIL_CONFIG: OnceCell<EmailConfig> = OnceCell::new();
 
+const DEFAULT_FROM: &str = "Affinity Bot <no-reply@affinity.com>";
+const DEFAULT_TIMEOUT_SECS: u64 = 30;
+const DEFAULT_POOL_SIZE: u32 = 4;
+const DEFAULT_EMAIL_DIR: &str = "emails";
+
+/// Where outgoing mail goes, picked with the `EMAIL_TRANSPORT` secret.
+///
+/// `smtp` is the real thing. `file` writes every message as an `.eml` file into
+/// `EMAIL_FILE_DIR`, and `stub` accepts and drops everything; both are meant for running
+/// the notification pipeline locally. A local fake SMTP server (e.g. Mailpit on port 1025)
+/// works with `smtp` plus `SMTP_TLS = "none"`.
+enum Mailer {
+    Smtp(AsyncSmtpTransport<Tokio1Executor>),
+    File(AsyncFileTransport<Tokio1Executor>),
+    Stub(AsyncStubTransport),
+}
+
+/// An image referenced from the HTML body as `cid:<content_id>`.
+pub struct InlineImage {
+    pub content_id: String,
+    pub content_type: String,
+    pub data: Vec<u8>,
+}
+
 pub struct EmailConfig {
-    smtp_host: String,
-    smtp_username: String,
-    smtp_password: String,
+    from: Mailbox,
+    mailer: Mailer,
 }
 
 impl EmailConfig {
     pub fn init(secrets: &SecretStore) -> Result<(), Box<dyn std::error::Error>> {
-        let config = Self {
-            smtp_host: secrets
-                .get("SMTP_HOST")
-                .ok_or("SMTP_HOST not found")?
-                .to_string(),
-            smtp_username: secrets
-                .get("SMTP_USERNAME")
-                .ok_or("SMTP_USERNAME not found")?
-                .to_string(),
-            smtp_password: secrets
-                .get("SMTP_PASSWORD")
-                .ok_or("SMTP_PASSWORD not found")?
-                .to_string(),
+        let from = secrets
+            .get("EMAIL_FROM")
+            .unwrap_or_else(|| DEFAULT_FROM.to_string())
+            .parse()?;
+
+        let mailer = match secrets
+            .get("EMAIL_TRANSPORT")
+            .unwrap_or_else(|| "smtp".to_string())
+            .as_str()
+        {
+            "smtp" => Mailer::Smtp(Self::smtp_transport(secrets)?),
+            "file" => Mailer::File(AsyncFileTransport::new(
+                secrets
+                    .get("EMAIL_FILE_DIR")
+                    .unwrap_or_else(|| DEFAULT_EMAIL_DIR.to_string()),
+            )),
+            "stub" => Mailer::Stub(AsyncStubTransport::new_ok()),
+            other => return Err(format!("Unknown EMAIL_TRANSPORT {other:?}").into()),
         };
+
         EMAIL_CONFIG
-            .set(config)
+            .set(Self { from, mailer })
             .map_err(|_| "EmailConfig already initialized")?;
         Ok(())
     }
 
+    // One long-lived transport; lettre keeps a pool of authenticated connections behind it
+    fn smtp_transport(
+        secrets: &SecretStore,
+    ) -> Result<AsyncSmtpTransport<Tokio1Executor>, Box<dyn std::error::Error>> {
+        let host = secrets.get("SMTP_HOST").ok_or("SMTP_HOST not found")?;
+
+        let mut builder = match secrets
+            .get("SMTP_TLS")
+            .unwrap_or_else(|| "starttls".to_string())
+            .as_str()
+        {
+            "starttls" => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&host)?,
+            "tls" => AsyncSmtpTransport::<Tokio1Executor>::relay(&host)?,
+            "none" => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&host),
+            other => return Err(format!("Unknown SMTP_TLS mode {other:?}").into()),
+        };
+
+        if let Some(port) = secrets.get("SMTP_PORT") {
+            builder = builder.port(port.parse()?);
+        }
+
+        // Local fake servers usually accept mail without logging in
+        if let (Some(username), Some(password)) =
+            (secrets.get("SMTP_USERNAME"), secrets.get("SMTP_PASSWORD"))
+        {
+            builder = builder.credentials(Credentials::new(username, password));
+        }
+
+        let timeout = match secrets.get("SMTP_TIMEOUT_SECS") {
+            Some(secs) => secs.parse()?,
+            None => DEFAULT_TIMEOUT_SECS,
+        };
+        let pool_size = match secrets.get("SMTP_POOL_SIZE") {
+            Some(size) => size.parse()?,
+            None => DEFAULT_POOL_SIZE,
+        };
+
+        Ok(builder
+            .timeout(Some(Duration::from_secs(timeout)))
+            .pool_config(PoolConfig::new().max_size(pool_size))
+            .build())
+    }
+
     pub fn get() -> &'static EmailConfig {
         EMAIL_CONFIG.get().expect("EmailConfig not initialized")
     }
 
+    /// Sends an HTML email. With an unsubscribe URL the message carries the
+    /// `List-Unsubscribe` headers for one-click unsubscribe (RFC 8058); inline images
+    /// turn it into a `multipart/related` message.
     pub async fn send_email(
         &self,
         to: String,
         subject: String,
         body: String,
+        unsubscribe_url: Option<&str>,
+        inline_images: &[InlineImage],
     ) -> Result<(), Box<dyn std::error::Error>> {
-        let email = Message::builder()
-            .from("no-reply@affinity.com".parse()?)
+        let builder = Message::builder()
+            .from(self.from.clone())
             .to(to.parse()?)
-            .subject(subject)
-            .header(lettre::message::header::ContentType::TEXT_HTML)
-            .body(body)?;
+            .subject(subject);
+
+        let mut email = if inline_images.is_empty() {
+            builder.header(ContentType::TEXT_HTML).body(body)?
+        } else {
+            let mut related = MultiPart::related().singlepart(SinglePart::html(body));
+            for image in inline_images {
+                related = related.singlepart(
+                    Attachment::new_inline(image.content_id.clone())
+                        .body(image.data.clone(), ContentType::parse(&image.content_type)?),
+                );
+            }
+            builder.multipart(related)?
+        };
 
-        let creds = Credentials::new(self.smtp_username.clone(), self.smtp_password.clone());
-        let transport = AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&self.smtp_host)?
-            .credentials(creds)
-            .build();
+        if let Some(url) = unsubscribe_url {
+            let headers = email.headers_mut();
+            headers.insert_raw(HeaderValue::new(
+                HeaderName::new_from_ascii_str("List-Unsubscribe"),
+                format!("<{}>", url),
+            ));
+            headers.insert_raw(HeaderValue::new(
+                HeaderName::new_from_ascii_str("List-Unsubscribe-Post"),
+                "List-Unsubscribe=One-Click".to_string(),
+            ));
+        }
+
+        self.send(email).await
+    }
 
-        transport.send(email).await?;
+    pub async fn send(&self, email: Message) -> Result<(), Box<dyn std::error::Error>> {
+        match &self.mailer {
+            Mailer::Smtp(transport) => {
+                transport.send(email).await?;
+            }
+            Mailer::File(transport) => {
+                let id = transport.send(email).await?;
+                tracing::info!("Email written to file {}", id);
+            }
+            Mailer::Stub(transport) => {
+                transport.send(email).await?;
+            }
+        }
         Ok(())
     }
 }
@@ -0,0 +1,202 @@
+use hmac::{Hmac, Mac};
+use once_cell::sync::OnceCell;
+use rand::RngCore;
+use sha2::Sha256;
+use shuttle_runtime::SecretStore;
+
+static LINK_CONFIG: OnceCell<LinkConfig> = OnceCell::new();
+
+const DEFAULT_HTTP_PORT: u16 = 8000;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Settings for the links embedded in emails and the HTTP server that answers them.
+///
+/// `PUBLIC_BASE_URL` is where the server is reachable from a mail client, e.g.
+/// `https://alerts.example.com`, and `LINK_SIGNING_SECRET` keys the per-preference tokens.
+/// Without a base URL, emails and alerts go out without links. The price export endpoint
+/// is only served when `EXPORT_API_TOKEN` is set.
+pub struct LinkConfig {
+    base_url: Option<String>,
+    signing_key: Vec<u8>,
+    port: u16,
+    export_token: Option<String>,
+}
+
+impl LinkConfig {
+    pub fn init(secrets: &SecretStore) -> Result<(), Box<dyn std::error::Error>> {
+        let base_url = secrets
+            .get("PUBLIC_BASE_URL")
+            .filter(|url| !url.is_empty())
+            .map(|url| url.trim_end_matches('/').to_string());
+        let signing_key = match (secrets.get("LINK_SIGNING_SECRET"), &base_url) {
+            (Some(secret), _) if !secret.is_empty() => secret.into_bytes(),
+            // Links signed with a throwaway key would break on the next restart
+            (_, Some(_)) => {
+                return Err("PUBLIC_BASE_URL is set but LINK_SIGNING_SECRET is not".into())
+            }
+            // Only the export token and verification codes use the key then; codes
+            // pending at a restart have to be requested again
+            (_, None) => {
+                tracing::warn!("PUBLIC_BASE_URL not set, emails and alerts are sent without manage and unsubscribe links");
+                let mut key = vec![0; 32];
+                rand::thread_rng().fill_bytes(&mut key);
+                key
+            }
+        };
+        let port = match secrets.get("HTTP_PORT") {
+            Some(port) => port.parse()?,
+            None => DEFAULT_HTTP_PORT,
+        };
+        let export_token = secrets
+            .get("EXPORT_API_TOKEN")
+            .filter(|token| !token.is_empty());
+
+        LINK_CONFIG
+            .set(Self {
+                base_url,
+                signing_key,
+                port,
+                export_token,
+            })
+            .map_err(|_| "LinkConfig already initialized")?;
+        Ok(())
+    }
+
+    pub fn get() -> &'static LinkConfig {
+        LINK_CONFIG.get().expect("LinkConfig not initialized")
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    pub fn export_enabled(&self) -> bool {
+        self.export_token.is_some()
+    }
+
+    /// Whether `token` is the export API token. Both sides are run through the HMAC so the
+    /// comparison takes the same time wherever they differ.
+    pub fn authorize_export(&self, token: &str) -> bool {
+        let Some(expected) = &self.export_token else {
+            return false;
+        };
+        let digest = |value: &str| {
+            let mut mac =
+                HmacSha256::new_from_slice(&self.signing_key).expect("HMAC accepts any key length");
+            mac.update(b"export:");
+            mac.update(value.as_bytes());
+            mac
+        };
+        digest(token)
+            .verify_slice(&digest(expected).finalize().into_bytes())
+            .is_ok()
+    }
+
+    fn code_mac(&self, email: &str, code: &str) -> HmacSha256 {
+        let mut mac =
+            HmacSha256::new_from_slice(&self.signing_key).expect("HMAC accepts any key length");
+        mac.update(b"verification:");
+        mac.update(email.as_bytes());
+        mac.update(b":");
+        mac.update(code.as_bytes());
+        mac
+    }
+
+    /// Hex HMAC of an email verification code, bound to the address it was sent to. Without
+    /// the key a leaked hash cannot be checked against the million possible codes.
+    pub fn hash_code(&self, email: &str, code: &str) -> String {
+        format!("{:x}", self.code_mac(email, code).finalize().into_bytes())
+    }
+
+    pub fn verify_code(&self, email: &str, code: &str, hash: &str) -> bool {
+        decode_hex(hash).is_some_and(|hash| self.code_mac(email, code).verify_slice(&hash).is_ok())
+    }
+
+    fn mac(&self, preference_id: i32) -> HmacSha256 {
+        let mut mac =
+            HmacSha256::new_from_slice(&self.signing_key).expect("HMAC accepts any key length");
+        mac.update(b"preference:");
+        mac.update(preference_id.to_string().as_bytes());
+        mac
+    }
+
+    /// Token of the form `<preference_id>.<hex hmac>`; it never expires, like the
+    /// unsubscribe links of most mailing lists.
+    pub fn sign(&self, preference_id: i32) -> String {
+        let signature = self.mac(preference_id).finalize().into_bytes();
+        format!("{}.{:x}", preference_id, signature)
+    }
+
+    /// Returns the preference id when the signature matches.
+    pub fn verify(&self, token: &str) -> Option<i32> {
+        let (id, signature) = token.split_once('.')?;
+        let preference_id = id.parse().ok()?;
+        let signature = decode_hex(signature)?;
+        self.mac(preference_id)
+            .verify_slice(&signature)
+            .ok()
+            .map(|_| preference_id)
+    }
+
+    /// `None` when links are turned off because `PUBLIC_BASE_URL` is not set.
+    pub fn manage_url(&self, preference_id: i32) -> Option<String> {
+        let base_url = self.base_url.as_ref()?;
+        Some(format!(
+            "{}/preferences/{}",
+            base_url,
+            self.sign(preference_id)
+        ))
+    }
+
+    pub fn unsubscribe_url(&self, preference_id: i32) -> Option<String> {
+        Some(format!("{}/unsubscribe", self.manage_url(preference_id)?))
+    }
+
+    fn email_mac(&self, email: &str) -> HmacSha256 {
+        let mut mac =
+            HmacSha256::new_from_slice(&self.signing_key).expect("HMAC accepts any key length");
+        mac.update(b"email:");
+        mac.update(email.as_bytes());
+        mac
+    }
+
+    /// Token for a whole address, `<hex email>.<hex hmac>`, for emails that cover several
+    /// preferences like digests.
+    pub fn sign_email(&self, email: &str) -> String {
+        let encoded: String = email.bytes().map(|byte| format!("{:02x}", byte)).collect();
+        let signature = self.email_mac(email).finalize().into_bytes();
+        format!("{}.{:x}", encoded, signature)
+    }
+
+    /// Returns the address when the signature matches.
+    pub fn verify_email(&self, token: &str) -> Option<String> {
+        let (encoded, signature) = token.split_once('.')?;
+        let email = String::from_utf8(decode_hex(encoded)?).ok()?;
+        let signature = decode_hex(signature)?;
+        self.email_mac(&email)
+            .verify_slice(&signature)
+            .ok()
+            .map(|_| email)
+    }
+
+    /// Stops every email alert to the address; `None` when links are turned off.
+    pub fn email_unsubscribe_url(&self, email: &str) -> Option<String> {
+        let base_url = self.base_url.as_ref()?;
+        Some(format!(
+            "{}/emails/{}/unsubscribe",
+            base_url,
+            self.sign_email(email)
+        ))
+    }
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
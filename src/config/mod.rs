@@ -1 +1,3 @@
 pub mod email;
+pub mod links;
+pub mod quotas;
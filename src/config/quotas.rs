@@ -0,0 +1,192 @@
+use ::entity::{channel_watchlists, notification_preferences};
+use once_cell::sync::OnceCell;
+use sea_orm::*;
+use serenity::all::{RoleId, UserId};
+use shuttle_runtime::SecretStore;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+static QUOTA_CONFIG: OnceCell<QuotaConfig> = OnceCell::new();
+
+const DEFAULT_USER_LIMIT: u64 = 10;
+const DEFAULT_GUILD_LIMIT: u64 = 200;
+const DEFAULT_MIN_INTERVAL_HOURS: i32 = 3;
+const DEFAULT_COOLDOWN_SECS: u64 = 30;
+
+/// Limits on how many trackers people can add and how often they may be alerted.
+///
+/// `TRACKER_LIMIT_PER_USER` and `TRACKER_LIMIT_PER_GUILD` cap the trackers of one member
+/// and of one server. `TRACKER_ROLE_LIMITS` raises the member limit for roles, as a comma
+/// separated list of `role_id:limit` pairs; a member gets the highest limit of their roles.
+/// `TRACKER_MIN_INTERVAL_HOURS` is the shortest `timeintreval` allowed and
+/// `TRACKER_COOLDOWN_SECS` how long a member waits between two new trackers.
+pub struct QuotaConfig {
+    user_limit: u64,
+    guild_limit: u64,
+    role_limits: HashMap<RoleId, u64>,
+    min_interval_hours: i32,
+    cooldown: Duration,
+    last_added: Mutex<HashMap<UserId, Instant>>,
+}
+
+impl QuotaConfig {
+    pub fn init(secrets: &SecretStore) -> Result<(), Box<dyn std::error::Error>> {
+        let user_limit = match secrets.get("TRACKER_LIMIT_PER_USER") {
+            Some(limit) => limit.parse()?,
+            None => DEFAULT_USER_LIMIT,
+        };
+        let guild_limit = match secrets.get("TRACKER_LIMIT_PER_GUILD") {
+            Some(limit) => limit.parse()?,
+            None => DEFAULT_GUILD_LIMIT,
+        };
+        let min_interval_hours = match secrets.get("TRACKER_MIN_INTERVAL_HOURS") {
+            Some(hours) => hours.parse()?,
+            None => DEFAULT_MIN_INTERVAL_HOURS,
+        };
+        let cooldown_secs = match secrets.get("TRACKER_COOLDOWN_SECS") {
+            Some(secs) => secs.parse()?,
+            None => DEFAULT_COOLDOWN_SECS,
+        };
+        let mut role_limits = HashMap::new();
+        for pair in secrets
+            .get("TRACKER_ROLE_LIMITS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|pair| !pair.is_empty())
+        {
+            let (role, limit) = pair.split_once(':').ok_or_else(|| {
+                format!("TRACKER_ROLE_LIMITS entry `{}` is not role_id:limit", pair)
+            })?;
+            role_limits.insert(RoleId::new(role.trim().parse()?), limit.trim().parse()?);
+        }
+        if min_interval_hours < 1 {
+            return Err("TRACKER_MIN_INTERVAL_HOURS must be at least 1".into());
+        }
+
+        QUOTA_CONFIG
+            .set(Self {
+                user_limit,
+                guild_limit,
+                role_limits,
+                min_interval_hours,
+                cooldown: Duration::from_secs(cooldown_secs),
+                last_added: Mutex::default(),
+            })
+            .map_err(|_| "QuotaConfig already initialized")?;
+        Ok(())
+    }
+
+    pub fn get() -> &'static QuotaConfig {
+        QUOTA_CONFIG.get().expect("QuotaConfig not initialized")
+    }
+
+    pub fn min_interval_hours(&self) -> i32 {
+        self.min_interval_hours
+    }
+
+    pub fn check_interval(&self, hours: i32) -> Result<(), String> {
+        if hours < self.min_interval_hours {
+            return Err(format!(
+                "⏱️ Alerts can be sent at most every {} hours. Please choose a `timeintreval` of {} or more.",
+                self.min_interval_hours, self.min_interval_hours
+            ));
+        }
+        Ok(())
+    }
+
+    /// Tracker limit of a member with `roles`.
+    pub fn user_limit(&self, roles: &[RoleId]) -> u64 {
+        roles
+            .iter()
+            .filter_map(|role| self.role_limits.get(role))
+            .copied()
+            .fold(self.user_limit, u64::max)
+    }
+
+    /// Says how long is left of the member's cooldown, if one is running.
+    pub fn check_cooldown(&self, user_id: UserId) -> Result<(), String> {
+        let last_added = self
+            .last_added
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(remaining) = last_added
+            .get(&user_id)
+            .map(|last| self.cooldown.saturating_sub(last.elapsed()))
+            .filter(|remaining| !remaining.is_zero())
+        {
+            return Err(format!(
+                "🐢 Slow down a little. You can add another tracker in {} seconds.",
+                remaining.as_secs().max(1)
+            ));
+        }
+        Ok(())
+    }
+
+    /// Starts the member's cooldown. Called once a tracker was added, so a failed add
+    /// costs no waiting.
+    pub fn start_cooldown(&self, user_id: UserId) {
+        let mut last_added = self
+            .last_added
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let now = Instant::now();
+        // Entries older than the cooldown no longer matter
+        last_added.retain(|_, last| now.duration_since(*last) < self.cooldown);
+        last_added.insert(user_id, now);
+    }
+
+    /// Whether the member and the guild may add one more tracker. Products added to a
+    /// channel watchlist count as trackers of whoever added them, and of their server.
+    pub async fn check_limits(
+        &self,
+        db: &DatabaseConnection,
+        user_id: UserId,
+        guild_id: Option<i64>,
+        roles: &[RoleId],
+    ) -> Result<(), String> {
+        let failed = |e: DbErr| {
+            tracing::error!("Failed to count trackers: {}", e);
+            "Something went wrong. Please try again later.".to_string()
+        };
+
+        let user_limit = self.user_limit(roles);
+        let user_trackers = notification_preferences::Entity::find()
+            .filter(notification_preferences::Column::DiscordUserId.eq(user_id.get() as i64))
+            .count(db)
+            .await
+            .map_err(failed)?
+            + channel_watchlists::Entity::find()
+                .filter(channel_watchlists::Column::AddedBy.eq(user_id.get() as i64))
+                .count(db)
+                .await
+                .map_err(failed)?;
+        if user_trackers >= user_limit {
+            return Err(format!(
+                "🚫 You already track {} products, the most you can. Remove one with `/untrack` or `/watchlist remove` to add another.",
+                user_trackers
+            ));
+        }
+
+        if let Some(guild_id) = guild_id {
+            let guild_trackers = notification_preferences::Entity::find()
+                .filter(notification_preferences::Column::GuildId.eq(guild_id))
+                .count(db)
+                .await
+                .map_err(failed)?
+                + channel_watchlists::Entity::find()
+                    .filter(channel_watchlists::Column::GuildId.eq(guild_id))
+                    .count(db)
+                    .await
+                    .map_err(failed)?;
+            if guild_trackers >= self.guild_limit {
+                return Err(format!(
+                    "🚫 This server has reached its limit of {} trackers. Ask someone to `/untrack` a product first.",
+                    self.guild_limit
+                ));
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,272 @@
+use ::entity::{notification_preferences, products};
+use async_trait::async_trait;
+use prelude::{DateTime, Decimal};
+use sea_orm::*;
+use serenity::all::{ChannelId, CreateMessage, Http, UserId};
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use super::deliveries::{
+    CHANNEL_DIGEST, CHANNEL_DISCORD, CHANNEL_DM, CHANNEL_EMAIL, CHANNEL_WEBHOOK,
+};
+use super::notifications::{Alert, Trigger};
+use crate::config::links::LinkConfig;
+use crate::utils::analytics::PriceAnalytics;
+use crate::utils::charts::price_chart;
+use crate::utils::emails::notify_price::PriceHistoryEmail;
+use crate::utils::embeds::alert_embed;
+use crate::utils::util::{product_display_name, product_url};
+use crate::utils::webhooks::post_json;
+
+/// What every channel renders for one preference in one run.
+pub struct AlertContext<'a> {
+    pub preference_id: i32,
+    pub product: &'a products::Model,
+    pub alerts: &'a [Alert],
+    /// Newest first, as stored in `price_history`.
+    pub history: &'a [(DateTime, Decimal)],
+    /// Price when the preference was last notified, for the change shown in Discord.
+    pub previous_price: Option<Decimal>,
+    /// Drawn on the price chart when set.
+    pub threshold: Option<Decimal>,
+}
+
+impl AlertContext<'_> {
+    pub fn current_price(&self) -> Decimal {
+        self.history
+            .first()
+            .map(|(_, price)| *price)
+            .unwrap_or(self.product.current_price)
+    }
+
+    /// Rule messages; the digest trigger has no message of its own worth showing.
+    pub fn messages(&self) -> Vec<String> {
+        self.alerts
+            .iter()
+            .filter(|alert| alert.trigger != Trigger::Digest)
+            .map(|alert| alert.message.clone())
+            .collect()
+    }
+}
+
+/// A destination for price alerts. A preference can have several; each one is logged
+/// separately in `notification_deliveries` under `name()`.
+#[async_trait]
+pub trait NotificationChannel: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// `Ok(Some(outbox_id))` when the message was queued for later sending, `Ok(None)`
+    /// when it was delivered right away.
+    async fn deliver(
+        &self,
+        db: &DatabaseConnection,
+        context: &AlertContext<'_>,
+    ) -> Result<Option<i32>, String>;
+}
+
+/// Builds the destinations enabled on a preference. Email alerts to an address in
+/// `digest_emails` are held for the digest instead of being sent one by one.
+pub fn channels_for(
+    preference: &notification_preferences::Model,
+    http: &Arc<Http>,
+    digest_emails: &HashSet<String>,
+) -> Vec<Box<dyn NotificationChannel>> {
+    let mut channels: Vec<Box<dyn NotificationChannel>> = Vec::new();
+
+    if preference.notify_email {
+        if let Some(email) = &preference.email {
+            if digest_emails.contains(email) {
+                channels.push(Box::new(DigestChannel));
+            } else {
+                channels.push(Box::new(EmailChannel { to: email.clone() }));
+            }
+        }
+    }
+    if preference.notify_dm {
+        if let Some(user_id) = preference.discord_user_id {
+            channels.push(Box::new(DiscordDmChannel {
+                http: http.clone(),
+                user_id: UserId::new(user_id as u64),
+            }));
+        }
+    }
+    if let Some(channel_id) = preference.discord_channel_id {
+        channels.push(Box::new(DiscordChannel {
+            http: http.clone(),
+            channel_id: ChannelId::new(channel_id as u64),
+        }));
+    }
+    if let Some(url) = &preference.webhook_url {
+        channels.push(Box::new(WebhookChannel { url: url.clone() }));
+    }
+
+    channels
+}
+
+pub struct EmailChannel {
+    to: String,
+}
+
+#[async_trait]
+impl NotificationChannel for EmailChannel {
+    fn name(&self) -> &'static str {
+        CHANNEL_EMAIL
+    }
+
+    async fn deliver(
+        &self,
+        db: &DatabaseConnection,
+        context: &AlertContext<'_>,
+    ) -> Result<Option<i32>, String> {
+        let current_price = context.current_price();
+        let prices = context.history.iter().map(|(_, price)| *price);
+        let highest_price = prices.clone().max().unwrap_or(current_price);
+        let lowest_price = prices.min().unwrap_or(current_price);
+
+        let links = LinkConfig::get();
+        let mut email = PriceHistoryEmail::new(
+            product_display_name(context.product),
+            current_price,
+            highest_price,
+            lowest_price,
+            context.history.to_vec(),
+            self.to.clone(),
+        )
+        .product_details(
+            context.product.image_url.clone(),
+            product_url(context.product),
+        )
+        .preference_links(
+            links.manage_url(context.preference_id),
+            links.unsubscribe_url(context.preference_id),
+        )
+        .alerts(context.messages());
+
+        if let Some(analytics) = PriceAnalytics::compute(context.history, current_price) {
+            email = email.analytics(analytics.summary());
+        }
+
+        // A missing chart should not hold back the alert itself
+        match price_chart(context.history, context.threshold) {
+            Ok(png) => email = email.chart(png),
+            Err(e) => tracing::warn!(
+                "No price chart for preference {}: {}",
+                context.preference_id,
+                e
+            ),
+        }
+
+        email.queue_price_history(db).await.map(Some)
+    }
+}
+
+/// Accepts alerts without sending anything; they are logged as batched and the digest
+/// worker sends them together on the recipient's cadence.
+pub struct DigestChannel;
+
+#[async_trait]
+impl NotificationChannel for DigestChannel {
+    fn name(&self) -> &'static str {
+        CHANNEL_DIGEST
+    }
+
+    async fn deliver(
+        &self,
+        _db: &DatabaseConnection,
+        _context: &AlertContext<'_>,
+    ) -> Result<Option<i32>, String> {
+        Ok(None)
+    }
+}
+
+pub struct DiscordDmChannel {
+    http: Arc<Http>,
+    user_id: UserId,
+}
+
+#[async_trait]
+impl NotificationChannel for DiscordDmChannel {
+    fn name(&self) -> &'static str {
+        CHANNEL_DM
+    }
+
+    async fn deliver(
+        &self,
+        _db: &DatabaseConnection,
+        context: &AlertContext<'_>,
+    ) -> Result<Option<i32>, String> {
+        let dm = self
+            .user_id
+            .create_dm_channel(&self.http)
+            .await
+            .map_err(|e| e.to_string())?;
+        dm.send_message(&self.http, CreateMessage::new().embed(alert_embed(context)))
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(None)
+    }
+}
+
+pub struct DiscordChannel {
+    http: Arc<Http>,
+    channel_id: ChannelId,
+}
+
+#[async_trait]
+impl NotificationChannel for DiscordChannel {
+    fn name(&self) -> &'static str {
+        CHANNEL_DISCORD
+    }
+
+    async fn deliver(
+        &self,
+        _db: &DatabaseConnection,
+        context: &AlertContext<'_>,
+    ) -> Result<Option<i32>, String> {
+        self.channel_id
+            .send_message(&self.http, CreateMessage::new().embed(alert_embed(context)))
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(None)
+    }
+}
+
+/// POSTs the alert as JSON, for chat tools and home automation that accept webhooks.
+pub struct WebhookChannel {
+    url: String,
+}
+
+#[async_trait]
+impl NotificationChannel for WebhookChannel {
+    fn name(&self) -> &'static str {
+        CHANNEL_WEBHOOK
+    }
+
+    async fn deliver(
+        &self,
+        _db: &DatabaseConnection,
+        context: &AlertContext<'_>,
+    ) -> Result<Option<i32>, String> {
+        let payload = serde_json::json!({
+            "preference_id": context.preference_id,
+            "product_id": context.product.product_id,
+            "product_name": product_display_name(context.product),
+            "product_url": product_url(context.product),
+            "image_url": context.product.image_url,
+            "price": context.current_price(),
+            "previous_price": context.previous_price,
+            "in_stock": context.product.in_stock,
+            "alerts": context
+                .alerts
+                .iter()
+                .map(|alert| serde_json::json!({
+                    "trigger": alert.trigger.as_str(),
+                    "message": alert.message,
+                }))
+                .collect::<Vec<_>>(),
+        });
+
+        post_json(&self.url, &payload).await?;
+        Ok(None)
+    }
+}
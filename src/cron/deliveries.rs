@@ -0,0 +1,81 @@
+use ::entity::notification_deliveries;
+use chrono::Utc;
+use sea_orm::*;
+
+use super::notifications::Alert;
+
+pub const STATUS_QUEUED: &str = "queued";
+pub const STATUS_SENT: &str = "sent";
+pub const STATUS_FAILED: &str = "failed";
+/// Held for the recipient's next digest email.
+pub const STATUS_BATCHED: &str = "batched";
+
+pub const CHANNEL_EMAIL: &str = "email";
+pub const CHANNEL_DM: &str = "discord_dm";
+pub const CHANNEL_DISCORD: &str = "discord_channel";
+pub const CHANNEL_WEBHOOK: &str = "webhook";
+pub const CHANNEL_DIGEST: &str = "email_digest";
+
+/// Whether this exact event was already delivered to the preference, so a retry or a
+/// repeated evaluation never announces the same drop twice.
+pub async fn already_delivered(
+    db: &impl ConnectionTrait,
+    preference_id: i32,
+    event_key: &str,
+) -> Result<bool, DbErr> {
+    let delivered = notification_deliveries::Entity::find()
+        .filter(notification_deliveries::Column::PreferenceId.eq(preference_id))
+        .filter(notification_deliveries::Column::EventKey.eq(event_key))
+        .filter(notification_deliveries::Column::Status.is_in([
+            STATUS_QUEUED,
+            STATUS_SENT,
+            STATUS_BATCHED,
+        ]))
+        .count(db)
+        .await?;
+    Ok(delivered > 0)
+}
+
+/// Logs one row per alert for a send attempt over `channel`.
+///
+/// `Ok(Some(outbox_id))` means the message was handed to the email outbox; the outbox
+/// worker flips those rows to sent or failed once it knows. Alerts accepted by the digest
+/// channel are held as batched until the digest worker queues them.
+pub async fn record_attempt(
+    db: &impl ConnectionTrait,
+    preference_id: i32,
+    alerts: &[Alert],
+    channel: &str,
+    result: &Result<Option<i32>, String>,
+) -> Result<(), DbErr> {
+    if alerts.is_empty() {
+        return Ok(());
+    }
+
+    let (status, error, outbox_id) = match result {
+        Ok(_) if channel == CHANNEL_DIGEST => (STATUS_BATCHED, None, None),
+        Ok(Some(outbox_id)) => (STATUS_QUEUED, None, Some(*outbox_id)),
+        Ok(None) => (STATUS_SENT, None, None),
+        Err(e) => (STATUS_FAILED, Some(e.clone()), None),
+    };
+
+    let rows = alerts
+        .iter()
+        .map(|alert| notification_deliveries::ActiveModel {
+            preference_id: Set(preference_id),
+            trigger: Set(alert.trigger.as_str().to_string()),
+            event_key: Set(alert.event_key.clone()),
+            price: Set(alert.price),
+            channel: Set(channel.to_string()),
+            status: Set(status.to_string()),
+            error: Set(error.clone()),
+            created_at: Set(Utc::now().naive_utc()),
+            outbox_id: Set(outbox_id),
+            ..Default::default()
+        });
+
+    notification_deliveries::Entity::insert_many(rows)
+        .exec(db)
+        .await?;
+    Ok(())
+}
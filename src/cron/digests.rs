@@ -0,0 +1,305 @@
+use ::entity::{
+    email_digests, notification_deliveries, notification_preferences, price_history, products,
+};
+use chrono::Utc;
+use sea_orm::*;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use tokio::time::{interval, Duration};
+
+use super::deliveries::{CHANNEL_DIGEST, STATUS_BATCHED, STATUS_QUEUED};
+use super::notifications::Trigger;
+use crate::config::links::LinkConfig;
+use crate::utils::emails::digest::{DigestEmail, DigestRow};
+use crate::utils::util::{product_display_name, product_url};
+
+/// How often a recipient in digest mode gets their batched alerts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cadence {
+    Daily,
+    Weekly,
+}
+
+impl Cadence {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Cadence::Daily => "daily",
+            Cadence::Weekly => "weekly",
+        }
+    }
+
+    pub fn parse(cadence: &str) -> Option<Self> {
+        match cadence {
+            "daily" => Some(Cadence::Daily),
+            "weekly" => Some(Cadence::Weekly),
+            _ => None,
+        }
+    }
+
+    fn period(&self) -> chrono::Duration {
+        match self {
+            Cadence::Daily => chrono::Duration::days(1),
+            Cadence::Weekly => chrono::Duration::weeks(1),
+        }
+    }
+}
+
+/// Addresses that get their email alerts as a digest.
+pub async fn digest_emails(db: &impl ConnectionTrait) -> Result<HashSet<String>, DbErr> {
+    Ok(email_digests::Entity::find()
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|digest| digest.email)
+        .collect())
+}
+
+/// Switches the addresses to digest mode, or back to one email per alert with `None`.
+/// The new cadence starts its first period now.
+pub async fn set_cadence(
+    db: &DatabaseConnection,
+    emails: &[String],
+    cadence: Option<Cadence>,
+) -> Result<(), DbErr> {
+    let Some(cadence) = cadence else {
+        email_digests::Entity::delete_many()
+            .filter(email_digests::Column::Email.is_in(emails.iter().cloned()))
+            .exec(db)
+            .await?;
+        return Ok(());
+    };
+
+    let now = Utc::now().naive_utc();
+    for email in emails {
+        let existing = email_digests::Entity::find()
+            .filter(email_digests::Column::Email.eq(email.as_str()))
+            .one(db)
+            .await?;
+        match existing {
+            Some(digest) => {
+                let mut update: email_digests::ActiveModel = digest.into();
+                update.cadence = Set(cadence.as_str().to_string());
+                update.last_sent_at = Set(now);
+                update.update(db).await?;
+            }
+            None => {
+                email_digests::ActiveModel {
+                    email: Set(email.clone()),
+                    cadence: Set(cadence.as_str().to_string()),
+                    last_sent_at: Set(now),
+                    created_at: Set(now),
+                    ..Default::default()
+                }
+                .insert(db)
+                .await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Sends each digest recipient their batched alerts once their cadence comes round.
+pub struct DigestWorker {
+    db: DatabaseConnection,
+}
+
+impl DigestWorker {
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self { db }
+    }
+
+    pub async fn start(self) {
+        let mut interval = interval(Duration::from_secs(3600));
+
+        tokio::spawn(async move {
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.send_due().await {
+                    tracing::error!("Error sending digests: {}", e);
+                }
+            }
+        });
+    }
+
+    async fn send_due(&self) -> Result<(), DbErr> {
+        let now = Utc::now().naive_utc();
+        let digests: HashMap<String, email_digests::Model> = email_digests::Entity::find()
+            .all(&self.db)
+            .await?
+            .into_iter()
+            .map(|digest| (digest.email.clone(), digest))
+            .collect();
+        let is_due = |digest: &email_digests::Model| {
+            Cadence::parse(&digest.cadence)
+                .is_none_or(|cadence| digest.last_sent_at + cadence.period() <= now)
+        };
+
+        let batched = notification_deliveries::Entity::find()
+            .filter(notification_deliveries::Column::Channel.eq(CHANNEL_DIGEST))
+            .filter(notification_deliveries::Column::Status.eq(STATUS_BATCHED))
+            .order_by(notification_deliveries::Column::CreatedAt, Order::Asc)
+            .find_also_related(notification_preferences::Entity)
+            .all(&self.db)
+            .await?;
+
+        // Recipient -> preference -> its batched alerts. Alerts of a recipient who has
+        // since left digest mode go out right away.
+        let mut by_recipient: HashMap<String, BTreeMap<i32, Batch>> = HashMap::new();
+        for (delivery, preference) in batched {
+            let Some(preference) = preference else {
+                continue;
+            };
+            let Some(email) = preference.email.clone() else {
+                continue;
+            };
+            if digests.get(&email).is_some_and(|digest| !is_due(digest)) {
+                continue;
+            }
+            by_recipient
+                .entry(email)
+                .or_default()
+                .entry(preference.preference_id)
+                .or_insert_with(|| Batch {
+                    preference,
+                    deliveries: Vec::new(),
+                })
+                .deliveries
+                .push(delivery);
+        }
+
+        let mut failed = HashSet::new();
+        for (email, batches) in by_recipient {
+            let cadence = digests
+                .get(&email)
+                .and_then(|digest| Cadence::parse(&digest.cadence));
+            let delivery_ids: Vec<i32> = batches
+                .values()
+                .flat_map(|batch| batch.deliveries.iter().map(|delivery| delivery.delivery_id))
+                .collect();
+            if let Err(e) = self.send_digest(&email, cadence, batches).await {
+                tracing::error!("Failed to send digest to {}: {}", email, e);
+                // The alerts stay batched with the reason, and the digest is tried again
+                // next hour instead of next period
+                notification_deliveries::Entity::update_many()
+                    .filter(notification_deliveries::Column::DeliveryId.is_in(delivery_ids))
+                    .set(notification_deliveries::ActiveModel {
+                        error: Set(Some(e)),
+                        ..Default::default()
+                    })
+                    .exec(&self.db)
+                    .await?;
+                failed.insert(email);
+            }
+        }
+
+        // Digests go out on a fixed schedule, also when there was nothing to send
+        let due_ids: Vec<i32> = digests
+            .values()
+            .filter(|digest| is_due(digest) && !failed.contains(&digest.email))
+            .map(|digest| digest.digest_id)
+            .collect();
+        if !due_ids.is_empty() {
+            email_digests::Entity::update_many()
+                .filter(email_digests::Column::DigestId.is_in(due_ids))
+                .set(email_digests::ActiveModel {
+                    last_sent_at: Set(now),
+                    ..Default::default()
+                })
+                .exec(&self.db)
+                .await?;
+        }
+        if !failed.is_empty() {
+            return Err(DbErr::Custom(format!(
+                "{} digests could not be queued",
+                failed.len()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Queues one email covering every batched alert of the recipient and hands the
+    /// delivery rows over to the outbox, which marks them sent or failed.
+    async fn send_digest(
+        &self,
+        email: &str,
+        cadence: Option<Cadence>,
+        batches: BTreeMap<i32, Batch>,
+    ) -> Result<(), String> {
+        let links = LinkConfig::get();
+        let mut rows = Vec::new();
+        let mut delivery_ids = Vec::new();
+
+        for (preference_id, batch) in batches {
+            delivery_ids.extend(batch.deliveries.iter().map(|delivery| delivery.delivery_id));
+
+            let Some(product) = products::Entity::find_by_id(batch.preference.product_id)
+                .one(&self.db)
+                .await
+                .map_err(|e| e.to_string())?
+            else {
+                continue;
+            };
+
+            // The change is measured from the price before the first batched alert
+            let since = batch
+                .deliveries
+                .iter()
+                .map(|delivery| delivery.created_at)
+                .min()
+                .unwrap_or(batch.preference.last_notified);
+            let previous_price = price_history::Entity::find()
+                .filter(price_history::Column::ProductId.eq(product.product_id))
+                .filter(price_history::Column::RecordedAt.lt(since))
+                .filter(price_history::Column::Price.gt(prelude::Decimal::ZERO))
+                .order_by(price_history::Column::RecordedAt, Order::Desc)
+                .one(&self.db)
+                .await
+                .map_err(|e| e.to_string())?
+                .map(|row| row.price);
+
+            let mut alerts: Vec<String> = Vec::new();
+            for delivery in &batch.deliveries {
+                let label = Trigger::parse(&delivery.trigger)
+                    .map(|trigger| trigger.label().to_string())
+                    .unwrap_or_else(|| delivery.trigger.clone());
+                if !alerts.contains(&label) {
+                    alerts.push(label);
+                }
+            }
+
+            rows.push(DigestRow {
+                product_name: product_display_name(&product),
+                product_url: product_url(&product),
+                current_price: product.current_price,
+                previous_price,
+                alerts,
+                manage_url: links.manage_url(preference_id),
+            });
+        }
+
+        if rows.is_empty() {
+            return Ok(());
+        }
+
+        let txn = self.db.begin().await.map_err(|e| e.to_string())?;
+        let outbox_id = DigestEmail::new(email.to_string(), cadence, rows)
+            .queue(&txn)
+            .await?;
+        notification_deliveries::Entity::update_many()
+            .filter(notification_deliveries::Column::DeliveryId.is_in(delivery_ids))
+            .set(notification_deliveries::ActiveModel {
+                status: Set(STATUS_QUEUED.to_string()),
+                outbox_id: Set(Some(outbox_id)),
+                error: Set(None),
+                ..Default::default()
+            })
+            .exec(&txn)
+            .await
+            .map_err(|e| e.to_string())?;
+        txn.commit().await.map_err(|e| e.to_string())
+    }
+}
+
+struct Batch {
+    preference: notification_preferences::Model,
+    deliveries: Vec<notification_deliveries::Model>,
+}
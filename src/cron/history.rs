@@ -0,0 +1,223 @@
+use ::entity::{price_history, price_history_daily};
+use chrono::{Duration as ChronoDuration, NaiveDate, Utc};
+use prelude::{DateTime, Decimal};
+use sea_orm::sea_query::Expr;
+use sea_orm::*;
+use shuttle_runtime::SecretStore;
+use tokio::time::{interval, Duration};
+
+const DEFAULT_RAW_DAYS: i64 = 90;
+// Ranges longer than this are read as one point per day
+const DETAILED_DAYS: i64 = 14;
+
+/// How long hourly `price_history` rows are kept before being rolled into daily rows,
+/// and how long the daily rows are kept. Set with `PRICE_HISTORY_RAW_DAYS` and
+/// `PRICE_HISTORY_DAILY_DAYS`; daily rows are kept forever when the latter is unset.
+pub struct RetentionConfig {
+    raw_days: i64,
+    daily_days: Option<i64>,
+}
+
+impl RetentionConfig {
+    pub fn from_secrets(secrets: &SecretStore) -> Result<Self, Box<dyn std::error::Error>> {
+        let raw_days = match secrets.get("PRICE_HISTORY_RAW_DAYS") {
+            Some(days) => days.parse()?,
+            None => DEFAULT_RAW_DAYS,
+        };
+        let daily_days = match secrets.get("PRICE_HISTORY_DAILY_DAYS") {
+            Some(days) => Some(days.parse()?),
+            None => None,
+        };
+        if raw_days < 1 {
+            return Err("PRICE_HISTORY_RAW_DAYS must be at least 1".into());
+        }
+        Ok(Self {
+            raw_days,
+            daily_days,
+        })
+    }
+}
+
+pub struct HistoryRetention {
+    db: DatabaseConnection,
+    config: RetentionConfig,
+}
+
+impl HistoryRetention {
+    pub fn new(db: DatabaseConnection, config: RetentionConfig) -> Self {
+        Self { db, config }
+    }
+
+    pub async fn start(self) {
+        let mut interval = interval(Duration::from_secs(24 * 3600));
+
+        tokio::spawn(async move {
+            loop {
+                interval.tick().await;
+                match self.run().await {
+                    Ok(rolled_up) => {
+                        tracing::info!("Rolled {} price history rows into daily rows", rolled_up)
+                    }
+                    Err(e) => tracing::error!("Error applying price history retention: {}", e),
+                }
+            }
+        });
+    }
+
+    /// Rolls every whole day older than the raw window into `price_history_daily` and
+    /// deletes the rolled-up rows. Returns how many raw rows were removed.
+    async fn run(&self) -> Result<u64, DbErr> {
+        let today = Utc::now().date_naive();
+        let cutoff = (today - ChronoDuration::days(self.config.raw_days))
+            .and_hms_opt(0, 0, 0)
+            .expect("midnight is a valid time");
+
+        let txn = self.db.begin().await?;
+
+        // Zero prices are failed scrapes and are dropped rather than aggregated
+        txn.execute(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            r#"INSERT INTO price_history_daily
+                   (product_id, day, min_price, max_price, close_price, samples)
+               SELECT product_id,
+                      recorded_at::date,
+                      MIN(price),
+                      MAX(price),
+                      (ARRAY_AGG(price ORDER BY recorded_at DESC))[1],
+                      COUNT(*)
+               FROM price_history
+               WHERE recorded_at < $1 AND price > 0
+               GROUP BY product_id, recorded_at::date
+               ON CONFLICT (product_id, day) DO UPDATE SET
+                   min_price = LEAST(price_history_daily.min_price, EXCLUDED.min_price),
+                   max_price = GREATEST(price_history_daily.max_price, EXCLUDED.max_price),
+                   close_price = EXCLUDED.close_price,
+                   samples = price_history_daily.samples + EXCLUDED.samples"#,
+            [cutoff.into()],
+        ))
+        .await?;
+
+        let removed = price_history::Entity::delete_many()
+            .filter(price_history::Column::RecordedAt.lt(cutoff))
+            .exec(&txn)
+            .await?
+            .rows_affected;
+
+        if let Some(daily_days) = self.config.daily_days {
+            price_history_daily::Entity::delete_many()
+                .filter(
+                    price_history_daily::Column::Day.lt(today - ChronoDuration::days(daily_days)),
+                )
+                .exec(&txn)
+                .await?;
+        }
+
+        txn.commit().await?;
+        Ok(removed)
+    }
+}
+
+#[derive(FromQueryResult)]
+struct DailyClose {
+    day: NaiveDate,
+    close_price: Decimal,
+}
+
+/// Price points of a product since `since`, newest first.
+///
+/// The last couple of weeks come from the hourly rows. Older days are one point each,
+/// the closing price, read from `price_history_daily` or aggregated on the fly from hourly
+/// rows the retention job has not rolled up yet.
+pub async fn price_series(
+    db: &DatabaseConnection,
+    product_id: i32,
+    since: DateTime,
+) -> Result<Vec<(DateTime, Decimal)>, DbErr> {
+    let detailed_since = (Utc::now().naive_utc() - ChronoDuration::days(DETAILED_DAYS))
+        .date()
+        .and_hms_opt(0, 0, 0)
+        .expect("midnight is a valid time")
+        .max(since);
+
+    let mut series: Vec<(DateTime, Decimal)> = price_history::Entity::find()
+        .filter(price_history::Column::ProductId.eq(product_id))
+        .filter(price_history::Column::RecordedAt.gte(detailed_since))
+        .order_by(price_history::Column::RecordedAt, Order::Desc)
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|row| (row.recorded_at, row.price))
+        .collect();
+
+    if since < detailed_since {
+        let daily = DailyClose::find_by_statement(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            r#"SELECT day, close_price FROM price_history_daily
+               WHERE product_id = $1 AND day >= $2 AND day < $3
+               UNION ALL
+               SELECT recorded_at::date AS day,
+                      (ARRAY_AGG(price ORDER BY recorded_at DESC))[1] AS close_price
+               FROM price_history
+               WHERE product_id = $1 AND price > 0 AND recorded_at >= $2 AND recorded_at < $3
+               GROUP BY recorded_at::date
+               ORDER BY day DESC"#,
+            [
+                product_id.into(),
+                since.date().into(),
+                detailed_since.date().into(),
+            ],
+        ))
+        .all(db)
+        .await?;
+
+        // A daily close stands at the end of its day
+        series.extend(
+            daily
+                .into_iter()
+                .filter_map(|row| Some((row.day.and_hms_opt(23, 59, 59)?, row.close_price))),
+        );
+    }
+
+    Ok(series)
+}
+
+/// Lowest and highest real prices recorded up to `until`, across hourly and daily rows.
+pub async fn price_extremes(
+    db: &DatabaseConnection,
+    product_id: i32,
+    until: DateTime,
+) -> Result<(Option<Decimal>, Option<Decimal>), DbErr> {
+    // Zero prices are failed scrapes, not real lows
+    let (raw_low, raw_high) = price_history::Entity::find()
+        .select_only()
+        .column_as(Expr::col(price_history::Column::Price).min(), "lowest")
+        .column_as(Expr::col(price_history::Column::Price).max(), "highest")
+        .filter(price_history::Column::ProductId.eq(product_id))
+        .filter(price_history::Column::RecordedAt.lte(until))
+        .filter(price_history::Column::Price.gt(Decimal::ZERO))
+        .into_tuple::<(Option<Decimal>, Option<Decimal>)>()
+        .one(db)
+        .await?
+        .unwrap_or_default();
+
+    let (daily_low, daily_high) = price_history_daily::Entity::find()
+        .select_only()
+        .column_as(
+            Expr::col(price_history_daily::Column::MinPrice).min(),
+            "lowest",
+        )
+        .column_as(
+            Expr::col(price_history_daily::Column::MaxPrice).max(),
+            "highest",
+        )
+        .filter(price_history_daily::Column::ProductId.eq(product_id))
+        .filter(price_history_daily::Column::Day.lt(until.date()))
+        .into_tuple::<(Option<Decimal>, Option<Decimal>)>()
+        .one(db)
+        .await?
+        .unwrap_or_default();
+
+    let lowest = [raw_low, daily_low].into_iter().flatten().min();
+    let highest = [raw_high, daily_high].into_iter().flatten().max();
+    Ok((lowest, highest))
+}
@@ -1 +1,7 @@
+pub mod channels;
+pub mod deliveries;
+pub mod digests;
+pub mod history;
 pub mod notifications;
+pub mod outbox;
+pub mod sale_summaries;
@@ -1,111 +1,506 @@
-use ::entity::{notification_preferences, price_history, products};
+use ::entity::{
+    notification_preferences, price_history, price_history_daily, product_sizes, products,
+};
 use async_trait::async_trait;
 use chrono::Utc;
 use prelude::Decimal;
+use sea_orm::sea_query::Expr;
 use sea_orm::*;
 use tokio::time::{interval, Duration};
 
-use crate::utils::emails::notify_price::PriceHistoryEmail;
+use serenity::all::Http;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use super::channels::{channels_for, AlertContext, NotificationChannel};
+use super::deliveries::{already_delivered, record_attempt};
+use super::digests::digest_emails;
+use super::history::{price_extremes, price_series};
+use super::sale_summaries::{is_sale_alert, open_sale, SaleSummaries, SUMMARIZED_CHANNELS};
+use crate::config::quotas::QuotaConfig;
+use crate::scraper::status::ScraperStatus;
+
+// How far back the chart and price list in a notification go
+const NOTIFICATION_HISTORY_DAYS: i64 = 90;
+
+/// Why a preference fired. Several triggers can fire in the same tick and are sent in one message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trigger {
+    BelowThreshold,
+    NewLow,
+    NewHigh,
+    PriceDrop,
+    BackInStock,
+    SizeRestocked,
+    DiscountReached,
+    Digest,
+}
+
+impl Trigger {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Trigger::BelowThreshold => "below_threshold",
+            Trigger::NewLow => "new_low",
+            Trigger::NewHigh => "new_high",
+            Trigger::PriceDrop => "price_drop",
+            Trigger::BackInStock => "back_in_stock",
+            Trigger::SizeRestocked => "size_restocked",
+            Trigger::DiscountReached => "discount_reached",
+            Trigger::Digest => "digest",
+        }
+    }
+
+    pub fn parse(trigger: &str) -> Option<Self> {
+        [
+            Trigger::BelowThreshold,
+            Trigger::NewLow,
+            Trigger::NewHigh,
+            Trigger::PriceDrop,
+            Trigger::BackInStock,
+            Trigger::SizeRestocked,
+            Trigger::DiscountReached,
+            Trigger::Digest,
+        ]
+        .into_iter()
+        .find(|candidate| candidate.as_str() == trigger)
+    }
+
+    /// Short description for places that list several alerts, like the digest table.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Trigger::BelowThreshold => "Below your target",
+            Trigger::NewLow => "New all-time low",
+            Trigger::NewHigh => "New all-time high",
+            Trigger::PriceDrop => "Price drop",
+            Trigger::BackInStock => "Back in stock",
+            Trigger::SizeRestocked => "Size restocked",
+            Trigger::DiscountReached => "Discount reached",
+            Trigger::Digest => "Scheduled update",
+        }
+    }
+}
+
+/// A fired rule ready to be sent. `event_key` identifies the underlying event (e.g. the
+/// price the product dropped to) and is what duplicate suppression compares against.
+#[derive(Clone)]
+pub struct Alert {
+    pub trigger: Trigger,
+    pub event_key: String,
+    pub price: Decimal,
+    /// Price when the preference was last notified, if known.
+    pub previous_price: Option<Decimal>,
+    pub message: String,
+}
+
+/// The product as it was at some moment. Days rolled up into `price_history_daily` only
+/// keep their closing price, so stock and discount are unknown for them.
+pub struct PricePoint {
+    price: Decimal,
+    in_stock: Option<bool>,
+    discount_percent: Option<i32>,
+}
+
+impl From<price_history::Model> for PricePoint {
+    fn from(row: price_history::Model) -> Self {
+        Self {
+            price: row.price,
+            in_stock: Some(row.in_stock),
+            discount_percent: Some(row.discount_percent.unwrap_or(0)),
+        }
+    }
+}
+
+/// What the rules look at: the latest scrape, and the product as it was when we last notified.
+pub struct PriceState {
+    latest: price_history::Model,
+    at_last_notified: Option<PricePoint>,
+    lowest_before: Option<Decimal>,
+    highest_before: Option<Decimal>,
+    sizes: Vec<product_sizes::Model>,
+}
+
+impl PriceState {
+    pub async fn load(
+        db: &DatabaseConnection,
+        product_id: i32,
+        last_notified: prelude::DateTime,
+    ) -> Result<Option<Self>, DbErr> {
+        let Some(latest) = price_history::Entity::find()
+            .filter(price_history::Column::ProductId.eq(product_id))
+            .order_by(price_history::Column::RecordedAt, Order::Desc)
+            .one(db)
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        let mut at_last_notified: Option<PricePoint> = price_history::Entity::find()
+            .filter(price_history::Column::ProductId.eq(product_id))
+            .filter(price_history::Column::RecordedAt.lte(last_notified))
+            .order_by(price_history::Column::RecordedAt, Order::Desc)
+            .one(db)
+            .await?
+            .map(PricePoint::from);
+        // Trackers quiet for longer than the raw window only have the daily close left;
+        // without it every rule would treat the product as new and fire
+        if at_last_notified.is_none() {
+            at_last_notified = price_history_daily::Entity::find()
+                .filter(price_history_daily::Column::ProductId.eq(product_id))
+                .filter(price_history_daily::Column::Day.lte(last_notified.date()))
+                .order_by(price_history_daily::Column::Day, Order::Desc)
+                .one(db)
+                .await?
+                .map(|row| PricePoint {
+                    price: row.close_price,
+                    in_stock: None,
+                    discount_percent: None,
+                });
+        }
+
+        let (lowest_before, highest_before) = price_extremes(db, product_id, last_notified).await?;
+
+        let sizes = product_sizes::Entity::find()
+            .filter(product_sizes::Column::ProductId.eq(product_id))
+            .all(db)
+            .await?;
+
+        Ok(Some(Self {
+            latest,
+            at_last_notified,
+            lowest_before,
+            highest_before,
+            sizes,
+        }))
+    }
+}
 
 // Trait for notification preferences
 #[async_trait]
 pub trait NotificationPreference {
-    async fn should_notify(&self, db: &DatabaseConnection) -> Result<bool, DbErr>;
-    async fn send_notification(&self, db: &DatabaseConnection) -> Result<(), DbErr>;
+    async fn evaluate(&self, db: &DatabaseConnection) -> Result<Vec<Alert>, DbErr>;
+    async fn send_notification(
+        &self,
+        db: &DatabaseConnection,
+        alerts: &[Alert],
+    ) -> Result<(), DbErr>;
     async fn update_last_notified(&self, db: &DatabaseConnection) -> Result<(), DbErr>;
 }
 
-impl From<notification_preferences::Model> for MyntraNotification {
-    fn from(model: notification_preferences::Model) -> Self {
+impl MyntraNotification {
+    pub fn new(
+        model: notification_preferences::Model,
+        http: &Arc<Http>,
+        digest_emails: &HashSet<String>,
+    ) -> Self {
         Self {
+            channels: channels_for(&model, http, digest_emails),
             preference_id: model.preference_id,
             product_id: model.product_id,
-            email: model.email,
-            time_interval: model.time_interval_hours,
+            // Trackers added before the minimum interval existed may ask for less
+            time_interval: model
+                .time_interval_hours
+                .max(QuotaConfig::get().min_interval_hours()),
             price_threshold: model.price_threshold,
             notify_on_lowest: model.notify_on_lowest,
             notify_on_highest: model.notify_on_highest,
             last_notified: chrono::DateTime::from_naive_utc_and_offset(model.last_notified, Utc),
+            watch_size: model.watch_size,
+            discount_threshold: model.discount_threshold,
+            drop_percent: model.drop_percent,
+            notify_on_restock: model.notify_on_restock,
+            periodic_digest: model.periodic_digest,
         }
     }
 }
 
-// Scope for improvement. Dead Code warning here , need to remove this
 pub struct MyntraNotification {
     preference_id: i32,
     product_id: i32,
-    email: String,
+    channels: Vec<Box<dyn NotificationChannel>>,
     time_interval: i32,
     price_threshold: Decimal,
     notify_on_lowest: bool,
     notify_on_highest: bool,
     last_notified: chrono::DateTime<Utc>,
+    watch_size: Option<String>,
+    discount_threshold: Option<i32>,
+    drop_percent: Option<i32>,
+    notify_on_restock: bool,
+    periodic_digest: bool,
 }
 
-#[async_trait]
-impl NotificationPreference for MyntraNotification {
-    // Scope of improvement
-    async fn should_notify(&self, _: &DatabaseConnection) -> Result<bool, DbErr> {
-        if Utc::now() - self.last_notified > chrono::Duration::hours(self.time_interval.into()) {
-            Ok(true)
-        } else {
-            Ok(false)
+impl MyntraNotification {
+    /// Evaluates every enabled rule against the current price state.
+    ///
+    /// Rules compare "now" with "when we last notified", so a condition that stays true
+    /// (e.g. the price sitting under the threshold) only fires once, when it first becomes true.
+    pub fn triggered_rules(&self, state: &PriceState) -> Vec<Trigger> {
+        let mut triggers = Vec::new();
+        let latest = &state.latest;
+        let previous = state.at_last_notified.as_ref();
+        let price = latest.price;
+        let scraped = !price.is_zero();
+
+        if scraped && self.price_threshold > Decimal::ZERO && price <= self.price_threshold {
+            let was_above = previous
+                .map(|row| row.price.is_zero() || row.price > self.price_threshold)
+                .unwrap_or(true);
+            if was_above {
+                triggers.push(Trigger::BelowThreshold);
+            }
+        }
+
+        if scraped && self.notify_on_lowest && state.lowest_before.is_some_and(|low| price < low) {
+            triggers.push(Trigger::NewLow);
         }
+
+        if scraped
+            && self.notify_on_highest
+            && state.highest_before.is_some_and(|high| price > high)
+        {
+            triggers.push(Trigger::NewHigh);
+        }
+
+        if let (Some(percent), Some(previous)) = (self.drop_percent, previous) {
+            if scraped
+                && !previous.price.is_zero()
+                && percent_change(previous.price, price) <= -Decimal::from(percent)
+            {
+                triggers.push(Trigger::PriceDrop);
+            }
+        }
+
+        if self.notify_on_restock
+            && latest.in_stock
+            && previous.is_some_and(|row| row.in_stock == Some(false))
+        {
+            triggers.push(Trigger::BackInStock);
+        }
+
+        if let Some(watch_size) = &self.watch_size {
+            let restocked = state.sizes.iter().any(|size| {
+                size.label.eq_ignore_ascii_case(watch_size)
+                    && size.in_stock
+                    && size
+                        .restocked_at
+                        .is_some_and(|restocked| restocked > self.last_notified.naive_utc())
+            });
+            if restocked {
+                triggers.push(Trigger::SizeRestocked);
+            }
+        }
+
+        if let Some(threshold) = self.discount_threshold {
+            let reached = latest.discount_percent.unwrap_or(0) >= threshold;
+            let was_below = previous.is_none_or(|row| {
+                row.discount_percent
+                    .is_some_and(|percent| percent < threshold)
+            });
+            if reached && was_below {
+                triggers.push(Trigger::DiscountReached);
+            }
+        }
+
+        if self.periodic_digest
+            && Utc::now() - self.last_notified > chrono::Duration::hours(self.time_interval.into())
+        {
+            triggers.push(Trigger::Digest);
+        }
+
+        triggers
     }
 
-    async fn send_notification(&self, db: &DatabaseConnection) -> Result<(), DbErr> {
-        let existing_product = products::Entity::find()
+    /// Sends the alerts over the channels not named in `skip`. Returns whether there was
+    /// any such channel; fails only when every one of them failed.
+    async fn send_over(
+        &self,
+        db: &DatabaseConnection,
+        alerts: &[Alert],
+        skip: &[&str],
+    ) -> Result<bool, DbErr> {
+        let channels: Vec<&dyn NotificationChannel> = self
+            .channels
+            .iter()
+            .map(|channel| channel.as_ref())
+            .filter(|channel| !skip.contains(&channel.name()))
+            .collect();
+        if channels.is_empty() {
+            return Ok(false);
+        }
+
+        let Some(product) = products::Entity::find()
             .filter(products::Column::ProductId.eq(self.product_id))
             .one(db)
-            .await?;
+            .await?
+        else {
+            return Ok(false);
+        };
 
-        let price_history = price_history::Entity::find()
-            .filter(price_history::Column::ProductId.eq(self.product_id))
-            .order_by(price_history::Column::RecordedAt, Order::Desc)
-            .all(db)
-            .await?;
+        let since = Utc::now().naive_utc() - chrono::Duration::days(NOTIFICATION_HISTORY_DAYS);
+        let history = price_series(db, self.product_id, since).await?;
+        let previous_price = history
+            .iter()
+            .find(|(recorded_at, _)| *recorded_at <= self.last_notified.naive_utc())
+            .map(|(_, price)| *price);
 
-        if let Some(product) = existing_product {
-            let prices: Vec<(prelude::DateTime, Decimal)> = price_history
-                .iter()
-                .map(|ph| (ph.recorded_at, ph.price))
-                .collect();
-
-            let current_price = prices.first().map(|(_, p)| p.clone()).unwrap_or_default();
-            let highest_price = prices
-                .iter()
-                .map(|(_, p)| p)
-                .max()
-                .unwrap_or(&current_price)
-                .clone();
-            let lowest_price = prices
-                .iter()
-                .map(|(_, p)| p)
-                .min()
-                .unwrap_or(&current_price)
-                .clone();
-
-            let email = PriceHistoryEmail::new(
-                product.product_id.to_string(), // Scope for Improvement
-                current_price,
-                highest_price,
-                lowest_price,
-                prices,
-                self.email.clone(),
+        let context = AlertContext {
+            preference_id: self.preference_id,
+            product: &product,
+            alerts,
+            history: &history,
+            previous_price,
+            threshold: Some(self.price_threshold).filter(|threshold| !threshold.is_zero()),
+        };
+
+        let mut errors = Vec::new();
+        for channel in &channels {
+            let result = channel.deliver(db, &context).await;
+            record_attempt(db, self.preference_id, alerts, channel.name(), &result).await?;
+            if let Err(e) = result {
+                errors.push(format!("{}: {}", channel.name(), e));
+            }
+        }
+
+        if !errors.is_empty() && errors.len() == channels.len() {
+            return Err(DbErr::Custom(errors.join("; ")));
+        }
+        if !errors.is_empty() {
+            tracing::warn!(
+                "Preference {} partially delivered: {}",
+                self.preference_id,
+                errors.join("; ")
             );
+        }
+        Ok(true)
+    }
 
-            email
-                .send_price_history()
-                .await
-                .map_err(|e| DbErr::Custom(e))?;
+    fn describe(&self, trigger: Trigger, state: &PriceState) -> String {
+        let price = state.latest.price;
+        match trigger {
+            Trigger::BelowThreshold => format!(
+                "Price is now ₹{}, below your target of ₹{}",
+                price, self.price_threshold
+            ),
+            Trigger::NewLow => format!(
+                "New all-time low of ₹{} (previous low ₹{})",
+                price,
+                state.lowest_before.unwrap_or_default()
+            ),
+            Trigger::NewHigh => format!(
+                "New all-time high of ₹{} (previous high ₹{})",
+                price,
+                state.highest_before.unwrap_or_default()
+            ),
+            Trigger::PriceDrop => {
+                let before = state
+                    .at_last_notified
+                    .as_ref()
+                    .map(|row| row.price)
+                    .unwrap_or_default();
+                format!(
+                    "Price fell {}% from ₹{} to ₹{}",
+                    -percent_change(before, price).round_dp(1),
+                    before,
+                    price
+                )
+            }
+            Trigger::BackInStock => "Back in stock".to_string(),
+            Trigger::SizeRestocked => format!(
+                "Size {} is back in stock",
+                self.watch_size.as_deref().unwrap_or_default()
+            ),
+            Trigger::DiscountReached => format!(
+                "Discount is now {}% (you asked for {}%)",
+                state.latest.discount_percent.unwrap_or(0),
+                self.discount_threshold.unwrap_or(0)
+            ),
+            Trigger::Digest => "Your scheduled price update".to_string(),
         }
+    }
 
-        Ok(())
+    fn event_key(&self, trigger: Trigger, state: &PriceState) -> String {
+        let latest = &state.latest;
+        match trigger {
+            // Keyed by the history row as well, so the same price reached again by a later
+            // scrape is a new event rather than a duplicate of the first one
+            Trigger::BelowThreshold | Trigger::NewLow | Trigger::NewHigh | Trigger::PriceDrop => {
+                format!(
+                    "{}:{}:{}",
+                    trigger.as_str(),
+                    latest.price.normalize(),
+                    latest.history_id
+                )
+            }
+            Trigger::DiscountReached => format!(
+                "{}:{}:{}",
+                trigger.as_str(),
+                latest.discount_percent.unwrap_or(0),
+                latest.history_id
+            ),
+            Trigger::BackInStock => format!("{}:{}", trigger.as_str(), latest.history_id),
+            Trigger::SizeRestocked => {
+                let restocked_at = state
+                    .sizes
+                    .iter()
+                    .filter(|size| {
+                        self.watch_size
+                            .as_deref()
+                            .is_some_and(|watch| size.label.eq_ignore_ascii_case(watch))
+                    })
+                    .filter_map(|size| size.restocked_at)
+                    .max()
+                    .map(|at| at.and_utc().timestamp())
+                    .unwrap_or_default();
+                format!("{}:{}", trigger.as_str(), restocked_at)
+            }
+            Trigger::Digest => format!("{}:{}", trigger.as_str(), Utc::now().timestamp()),
+        }
+    }
+}
+
+pub fn percent_change(from: Decimal, to: Decimal) -> Decimal {
+    (to - from) * Decimal::from(100) / from
+}
+
+#[async_trait]
+impl NotificationPreference for MyntraNotification {
+    async fn evaluate(&self, db: &DatabaseConnection) -> Result<Vec<Alert>, DbErr> {
+        let Some(state) =
+            PriceState::load(db, self.product_id, self.last_notified.naive_utc()).await?
+        else {
+            return Ok(Vec::new());
+        };
+
+        let mut alerts = Vec::new();
+        for trigger in self.triggered_rules(&state) {
+            let event_key = self.event_key(trigger, &state);
+            if already_delivered(db, self.preference_id, &event_key).await? {
+                continue;
+            }
+            alerts.push(Alert {
+                trigger,
+                event_key,
+                price: state.latest.price,
+                previous_price: state.at_last_notified.as_ref().map(|point| point.price),
+                message: self.describe(trigger, &state),
+            });
+        }
+        Ok(alerts)
+    }
+
+    /// Sends the alerts to every channel of the preference. Fails only when no channel
+    /// accepted them, so a broken webhook does not hold back the email.
+    async fn send_notification(
+        &self,
+        db: &DatabaseConnection,
+        alerts: &[Alert],
+    ) -> Result<(), DbErr> {
+        self.send_over(db, alerts, &[]).await.map(|_| ())
     }
 
     async fn update_last_notified(&self, db: &DatabaseConnection) -> Result<(), DbErr> {
         notification_preferences::Entity::update_many()
-            .filter(notification_preferences::Column::ProductId.eq(self.product_id))
+            .filter(notification_preferences::Column::PreferenceId.eq(self.preference_id))
             .set(notification_preferences::ActiveModel {
                 last_notified: Set(Utc::now().naive_utc()),
                 ..Default::default()
@@ -128,20 +523,137 @@ pub trait NotificationHandler: Send + Sync {
 }
 
 // Handler implementations
-pub struct MyntraHandler;
+pub struct MyntraHandler {
+    http: Arc<Http>,
+}
+
+impl MyntraHandler {
+    /// `http` is used for Discord DM and channel alerts outside of any event handler.
+    pub fn new(http: Arc<Http>) -> Self {
+        Self { http }
+    }
+}
 
 #[async_trait]
 impl NotificationHandler for MyntraHandler {
     async fn check_notifications(&self, db: &DatabaseConnection) -> Result<(), DbErr> {
-        let preferences = notification_preferences::Entity::find().all(db).await?;
+        // Waits for a scrape run in progress, whose end decides whether a sale started
+        let _run = ScraperStatus::get().lock_run().await;
+        let preferences = notification_preferences::Entity::find()
+            .filter(notification_preferences::Column::Active.eq(true))
+            .filter(notification_preferences::Column::Paused.eq(false))
+            .filter(
+                Condition::any()
+                    .add(notification_preferences::Column::SnoozedUntil.is_null())
+                    .add(
+                        notification_preferences::Column::SnoozedUntil.lte(Utc::now().naive_utc()),
+                    ),
+            )
+            .all(db)
+            .await?;
+        let digest_emails = digest_emails(db).await?;
+        let sale = open_sale(db).await?;
+        let mut summaries = SaleSummaries::default();
+        // Preferences with drops in a sale, sent once the summaries went out
+        let mut held = Vec::new();
 
         for pref in preferences {
-            let notification = MyntraNotification::from(pref);
-            if notification.should_notify(db).await? {
-                if notification.send_notification(db).await.is_ok() {
-                    notification.update_last_notified(db).await?;
+            let notification = MyntraNotification::new(pref.clone(), &self.http, &digest_emails);
+            // One broken preference must not hold back everyone else's alerts
+            let alerts = match notification.evaluate(db).await {
+                Ok(alerts) => alerts,
+                Err(e) => {
+                    tracing::error!(
+                        "Failed to evaluate preference {}: {}",
+                        notification.preference_id,
+                        e
+                    );
+                    continue;
+                }
+            };
+            if alerts.is_empty() {
+                continue;
+            }
+            tracing::info!(
+                "Preference {} triggered: {}",
+                notification.preference_id,
+                alerts
+                    .iter()
+                    .map(|alert| alert.trigger.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+
+            // During a sale, drops go into one summary per recipient instead of a message
+            // per product; webhooks and digests get them as usual
+            if sale.is_some() && alerts.iter().any(is_sale_alert) {
+                let drops: Vec<Alert> = alerts
+                    .iter()
+                    .filter(|alert| is_sale_alert(alert))
+                    .cloned()
+                    .collect();
+                let in_summary = summaries.add(&pref, &digest_emails, &drops);
+                held.push((notification, alerts, in_summary));
+                continue;
+            }
+
+            match notification.send_notification(db, &alerts).await {
+                Ok(()) => notification.update_last_notified(db).await?,
+                Err(e) => tracing::error!(
+                    "Failed to notify preference {}: {}",
+                    notification.preference_id,
+                    e
+                ),
+            }
+        }
+
+        let summarized = summaries.send(db, &self.http).await?;
+        let mut notified = Vec::new();
+        for (notification, alerts, in_summary) in held {
+            let preference_id = notification.preference_id;
+            // Sending the rest first would mark the drops delivered and lose them for the
+            // summary; the whole preference waits for the next run instead
+            if in_summary && !summarized.contains(&preference_id) {
+                tracing::warn!(
+                    "Sale summary for preference {} failed, retrying next run",
+                    preference_id
+                );
+                continue;
+            }
+
+            let (drops, other): (Vec<Alert>, Vec<Alert>) =
+                alerts.into_iter().partition(is_sale_alert);
+            let mut reached = in_summary;
+            match notification
+                .send_over(db, &drops, &SUMMARIZED_CHANNELS)
+                .await
+            {
+                Ok(sent) => reached |= sent,
+                Err(e) => tracing::error!("Failed to notify preference {}: {}", preference_id, e),
+            }
+            if !other.is_empty() {
+                match notification.send_notification(db, &other).await {
+                    Ok(()) => reached = true,
+                    Err(e) => {
+                        tracing::error!("Failed to notify preference {}: {}", preference_id, e)
+                    }
                 }
             }
+            // Only preferences a message reached move on; the others retry next run
+            if reached {
+                notified.push(preference_id);
+            }
+        }
+
+        if !notified.is_empty() {
+            notification_preferences::Entity::update_many()
+                .col_expr(
+                    notification_preferences::Column::LastNotified,
+                    Expr::value(Utc::now().naive_utc()),
+                )
+                .filter(notification_preferences::Column::PreferenceId.is_in(notified))
+                .exec(db)
+                .await?;
         }
         Ok(())
     }
@@ -0,0 +1,331 @@
+use ::entity::{email_attachments, email_outbox, notification_deliveries};
+use chrono::Utc;
+use sea_orm::sea_query::{Query, SimpleExpr};
+use sea_orm::*;
+use shuttle_runtime::SecretStore;
+use tokio::time::{interval, Duration};
+
+use super::deliveries::{STATUS_FAILED, STATUS_QUEUED, STATUS_SENT};
+use crate::config::email::{EmailConfig, InlineImage};
+
+pub const STATUS_PENDING: &str = "pending";
+
+const DEFAULT_MAX_ATTEMPTS: i32 = 6;
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+const BATCH_SIZE: u64 = 20;
+const BASE_BACKOFF_SECS: i64 = 60;
+const MAX_BACKOFF_SECS: i64 = 6 * 3600;
+const DEFAULT_SENT_RETENTION_DAYS: i64 = 30;
+const SWEEP_INTERVAL: Duration = Duration::from_secs(24 * 3600);
+
+/// Stores an email for the sender worker instead of sending it inline, so an SMTP outage
+/// delays the message rather than losing it. Returns the outbox id.
+///
+/// Emails sent on behalf of a preference pass its unsubscribe URL, which the worker turns
+/// into `List-Unsubscribe` headers. Inline images are stored next to the message.
+pub async fn enqueue_email(
+    db: &(impl ConnectionTrait + TransactionTrait),
+    to: String,
+    subject: String,
+    body: String,
+    unsubscribe_url: Option<String>,
+    inline_images: Vec<InlineImage>,
+) -> Result<i32, DbErr> {
+    let now = Utc::now().naive_utc();
+    let txn = db.begin().await?;
+    let row = email_outbox::ActiveModel {
+        recipient: Set(to),
+        subject: Set(subject),
+        body: Set(body),
+        status: Set(STATUS_PENDING.to_string()),
+        attempts: Set(0),
+        max_attempts: Set(DEFAULT_MAX_ATTEMPTS),
+        next_attempt_at: Set(now),
+        last_error: Set(None),
+        created_at: Set(now),
+        sent_at: Set(None),
+        unsubscribe_url: Set(unsubscribe_url),
+        ..Default::default()
+    }
+    .insert(&txn)
+    .await?;
+
+    if !inline_images.is_empty() {
+        email_attachments::Entity::insert_many(inline_images.into_iter().map(|image| {
+            email_attachments::ActiveModel {
+                outbox_id: Set(row.outbox_id),
+                content_id: Set(image.content_id),
+                content_type: Set(image.content_type),
+                data: Set(image.data),
+                ..Default::default()
+            }
+        }))
+        .exec(&txn)
+        .await?;
+    }
+
+    txn.commit().await?;
+    Ok(row.outbox_id)
+}
+
+/// Puts a failed message back in the queue with a fresh attempt budget. Its deliveries
+/// go back to queued with it, so the worker marks them sent once it goes out.
+pub async fn requeue(db: &DatabaseConnection, outbox_id: i32) -> Result<bool, DbErr> {
+    let Some(row) = email_outbox::Entity::find_by_id(outbox_id).one(db).await? else {
+        return Ok(false);
+    };
+    if row.status != STATUS_FAILED {
+        return Ok(false);
+    }
+
+    let txn = db.begin().await?;
+    let mut update: email_outbox::ActiveModel = row.into();
+    update.status = Set(STATUS_PENDING.to_string());
+    update.attempts = Set(0);
+    update.next_attempt_at = Set(Utc::now().naive_utc());
+    update.update(&txn).await?;
+
+    requeue_deliveries(
+        &txn,
+        notification_deliveries::Column::OutboxId.eq(outbox_id),
+    )
+    .await?;
+    txn.commit().await?;
+    Ok(true)
+}
+
+pub async fn requeue_all_failed(db: &DatabaseConnection) -> Result<u64, DbErr> {
+    let txn = db.begin().await?;
+    // Deliveries first, while their messages can still be found by the failed status
+    let failed = Query::select()
+        .column(email_outbox::Column::OutboxId)
+        .from(email_outbox::Entity)
+        .and_where(email_outbox::Column::Status.eq(STATUS_FAILED))
+        .to_owned();
+    requeue_deliveries(
+        &txn,
+        notification_deliveries::Column::OutboxId.in_subquery(failed),
+    )
+    .await?;
+
+    let result = email_outbox::Entity::update_many()
+        .filter(email_outbox::Column::Status.eq(STATUS_FAILED))
+        .set(email_outbox::ActiveModel {
+            status: Set(STATUS_PENDING.to_string()),
+            attempts: Set(0),
+            next_attempt_at: Set(Utc::now().naive_utc()),
+            ..Default::default()
+        })
+        .exec(&txn)
+        .await?;
+    txn.commit().await?;
+    Ok(result.rows_affected)
+}
+
+async fn requeue_deliveries(
+    txn: &DatabaseTransaction,
+    outbox_filter: SimpleExpr,
+) -> Result<(), DbErr> {
+    notification_deliveries::Entity::update_many()
+        .filter(outbox_filter)
+        .filter(notification_deliveries::Column::Status.eq(STATUS_FAILED))
+        .set(notification_deliveries::ActiveModel {
+            status: Set(STATUS_QUEUED.to_string()),
+            error: Set(None),
+            ..Default::default()
+        })
+        .exec(txn)
+        .await?;
+    Ok(())
+}
+
+pub async fn recent_failures(
+    db: &DatabaseConnection,
+    limit: u64,
+) -> Result<Vec<email_outbox::Model>, DbErr> {
+    email_outbox::Entity::find()
+        .filter(email_outbox::Column::Status.eq(STATUS_FAILED))
+        .order_by(email_outbox::Column::CreatedAt, Order::Desc)
+        .limit(limit)
+        .all(db)
+        .await
+}
+
+/// Sends queued emails and, once a day, deletes sent ones older than
+/// `EMAIL_OUTBOX_RETENTION_DAYS` together with their inline images. Failed emails stay
+/// until an admin requeues them.
+pub struct OutboxWorker {
+    db: DatabaseConnection,
+    sent_retention_days: i64,
+}
+
+impl OutboxWorker {
+    pub fn from_secrets(
+        db: DatabaseConnection,
+        secrets: &SecretStore,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let sent_retention_days = match secrets.get("EMAIL_OUTBOX_RETENTION_DAYS") {
+            Some(days) => days.parse()?,
+            None => DEFAULT_SENT_RETENTION_DAYS,
+        };
+        if sent_retention_days < 1 {
+            return Err("EMAIL_OUTBOX_RETENTION_DAYS must be at least 1".into());
+        }
+        Ok(Self {
+            db,
+            sent_retention_days,
+        })
+    }
+
+    pub async fn start(self) {
+        let mut poll = interval(POLL_INTERVAL);
+        let mut sweep = interval(SWEEP_INTERVAL);
+
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = poll.tick() => {
+                        if let Err(e) = self.send_due().await {
+                            tracing::error!("Error draining email outbox: {}", e);
+                        }
+                    }
+                    _ = sweep.tick() => match self.delete_sent().await {
+                        Ok(deleted) => {
+                            tracing::info!("Deleted {} sent emails from the outbox", deleted)
+                        }
+                        Err(e) => tracing::error!("Error deleting sent emails: {}", e),
+                    },
+                }
+            }
+        });
+    }
+
+    async fn delete_sent(&self) -> Result<u64, DbErr> {
+        let cutoff = Utc::now().naive_utc() - chrono::Duration::days(self.sent_retention_days);
+        let txn = self.db.begin().await?;
+        let old_sent = Query::select()
+            .column(email_outbox::Column::OutboxId)
+            .from(email_outbox::Entity)
+            .and_where(email_outbox::Column::Status.eq(STATUS_SENT))
+            .and_where(email_outbox::Column::SentAt.lt(cutoff))
+            .to_owned();
+        // The chart images are most of what these rows weigh
+        email_attachments::Entity::delete_many()
+            .filter(email_attachments::Column::OutboxId.in_subquery(old_sent))
+            .exec(&txn)
+            .await?;
+        let deleted = email_outbox::Entity::delete_many()
+            .filter(email_outbox::Column::Status.eq(STATUS_SENT))
+            .filter(email_outbox::Column::SentAt.lt(cutoff))
+            .exec(&txn)
+            .await?
+            .rows_affected;
+        txn.commit().await?;
+        Ok(deleted)
+    }
+
+    async fn send_due(&self) -> Result<(), DbErr> {
+        let due = email_outbox::Entity::find()
+            .filter(email_outbox::Column::Status.eq(STATUS_PENDING))
+            .filter(email_outbox::Column::NextAttemptAt.lte(Utc::now().naive_utc()))
+            .order_by(email_outbox::Column::NextAttemptAt, Order::Asc)
+            .limit(BATCH_SIZE)
+            .all(&self.db)
+            .await?;
+
+        for message in due {
+            let inline_images: Vec<InlineImage> = email_attachments::Entity::find()
+                .filter(email_attachments::Column::OutboxId.eq(message.outbox_id))
+                .all(&self.db)
+                .await?
+                .into_iter()
+                .map(|attachment| InlineImage {
+                    content_id: attachment.content_id,
+                    content_type: attachment.content_type,
+                    data: attachment.data,
+                })
+                .collect();
+
+            let result = EmailConfig::get()
+                .send_email(
+                    message.recipient.clone(),
+                    message.subject.clone(),
+                    message.body.clone(),
+                    message.unsubscribe_url.as_deref(),
+                    &inline_images,
+                )
+                .await
+                .map_err(|e| (e.to_string(), is_permanent(e.as_ref())));
+
+            self.record_result(message, result).await?;
+        }
+        Ok(())
+    }
+
+    async fn record_result(
+        &self,
+        message: email_outbox::Model,
+        result: Result<(), (String, bool)>,
+    ) -> Result<(), DbErr> {
+        let outbox_id = message.outbox_id;
+        let attempts = message.attempts + 1;
+        let max_attempts = message.max_attempts;
+        let mut update: email_outbox::ActiveModel = message.into();
+        update.attempts = Set(attempts);
+
+        let final_status = match result {
+            Ok(()) => {
+                update.status = Set(STATUS_SENT.to_string());
+                update.sent_at = Set(Some(Utc::now().naive_utc()));
+                update.last_error = Set(None);
+                Some(STATUS_SENT)
+            }
+            Err((error, permanent)) => {
+                tracing::warn!(
+                    "Email {} failed (attempt {}): {}",
+                    outbox_id,
+                    attempts,
+                    error
+                );
+                update.last_error = Set(Some(error));
+                if permanent || attempts >= max_attempts {
+                    update.status = Set(STATUS_FAILED.to_string());
+                    Some(STATUS_FAILED)
+                } else {
+                    update.next_attempt_at = Set(Utc::now().naive_utc() + backoff(attempts));
+                    None
+                }
+            }
+        };
+        update.update(&self.db).await?;
+
+        if let Some(status) = final_status {
+            notification_deliveries::Entity::update_many()
+                .filter(notification_deliveries::Column::OutboxId.eq(outbox_id))
+                .set(notification_deliveries::ActiveModel {
+                    status: Set(status.to_string()),
+                    ..Default::default()
+                })
+                .exec(&self.db)
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+// 1m, 2m, 4m, ... capped at six hours
+fn backoff(attempts: i32) -> chrono::Duration {
+    let exponent = (attempts - 1).clamp(0, 16) as u32;
+    let secs = BASE_BACKOFF_SECS.saturating_mul(2_i64.pow(exponent));
+    chrono::Duration::seconds(secs.min(MAX_BACKOFF_SECS))
+}
+
+// Bad addresses, unbuildable messages and 5xx SMTP replies will not succeed on retry
+fn is_permanent(error: &(dyn std::error::Error + 'static)) -> bool {
+    if error.is::<lettre::address::AddressError>() || error.is::<lettre::error::Error>() {
+        return true;
+    }
+    error
+        .downcast_ref::<lettre::transport::smtp::Error>()
+        .is_some_and(|e| e.is_permanent())
+}
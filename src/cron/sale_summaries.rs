@@ -0,0 +1,264 @@
+use ::entity::{notification_preferences, products, sale_events};
+use sea_orm::prelude::Decimal;
+use sea_orm::*;
+use serenity::all::{ChannelId, CreateMessage, Http, UserId};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use super::deliveries::{record_attempt, CHANNEL_DISCORD, CHANNEL_DM, CHANNEL_EMAIL};
+use super::notifications::{percent_change, Alert, Trigger};
+use crate::config::links::LinkConfig;
+use crate::utils::emails::digest::{DigestEmail, DigestRow};
+use crate::utils::embeds::summary_embed;
+use crate::utils::util::{product_display_name, product_url};
+
+/// Channels whose drop alerts are folded into the sale summary. Webhooks and digest
+/// emails keep getting them as usual; a digest already is a summary.
+pub const SUMMARIZED_CHANNELS: [&str; 3] = [CHANNEL_DM, CHANNEL_DISCORD, CHANNEL_EMAIL];
+
+/// The store-wide sale going on right now, if any.
+pub async fn open_sale(db: &DatabaseConnection) -> Result<Option<sale_events::Model>, DbErr> {
+    sale_events::Entity::find()
+        .filter(sale_events::Column::EndedAt.is_null())
+        .order_by_desc(sale_events::Column::StartedAt)
+        .one(db)
+        .await
+}
+
+/// Alerts that a sale summary covers: the product got cheaper.
+pub fn is_sale_alert(alert: &Alert) -> bool {
+    matches!(
+        alert.trigger,
+        Trigger::BelowThreshold | Trigger::NewLow | Trigger::PriceDrop | Trigger::DiscountReached
+    )
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum Recipient {
+    Dm(i64),
+    Channel(i64),
+    Email(String),
+}
+
+impl Recipient {
+    fn channel_name(&self) -> &'static str {
+        match self {
+            Recipient::Dm(_) => CHANNEL_DM,
+            Recipient::Channel(_) => CHANNEL_DISCORD,
+            Recipient::Email(_) => CHANNEL_EMAIL,
+        }
+    }
+}
+
+struct Entry {
+    preference_id: i32,
+    product_id: i32,
+    previous_price: Option<Decimal>,
+    alerts: Vec<Alert>,
+}
+
+impl Entry {
+    // Negative for a drop; unknown when there was no price to compare with
+    fn change_percent(&self, product: &products::Model) -> Option<Decimal> {
+        self.previous_price
+            .filter(|previous| !previous.is_zero())
+            .map(|previous| percent_change(previous, product.current_price))
+    }
+}
+
+/// Drop alerts of one notification run during a sale, sent as one summary per DM, alert
+/// channel and email address instead of one message per product.
+#[derive(Default)]
+pub struct SaleSummaries {
+    entries: Vec<Entry>,
+    recipients: HashMap<Recipient, Vec<usize>>,
+}
+
+impl SaleSummaries {
+    /// Adds the alerts for every summarized destination of the preference and returns
+    /// whether there was one; a preference that only posts to a webhook or a digest adds
+    /// nothing.
+    pub fn add(
+        &mut self,
+        preference: &notification_preferences::Model,
+        digest_emails: &HashSet<String>,
+        alerts: &[Alert],
+    ) -> bool {
+        let mut recipients = Vec::new();
+        if let (true, Some(user_id)) = (preference.notify_dm, preference.discord_user_id) {
+            recipients.push(Recipient::Dm(user_id));
+        }
+        if let Some(channel_id) = preference.discord_channel_id {
+            recipients.push(Recipient::Channel(channel_id));
+        }
+        if let (true, Some(email)) = (preference.notify_email, &preference.email) {
+            if !digest_emails.contains(email) {
+                recipients.push(Recipient::Email(email.clone()));
+            }
+        }
+        if recipients.is_empty() {
+            return false;
+        }
+
+        let index = self.entries.len();
+        self.entries.push(Entry {
+            preference_id: preference.preference_id,
+            product_id: preference.product_id,
+            previous_price: alerts.iter().find_map(|alert| alert.previous_price),
+            alerts: alerts.to_vec(),
+        });
+        for recipient in recipients {
+            self.recipients.entry(recipient).or_default().push(index);
+        }
+        true
+    }
+
+    /// Sends every summary and logs the deliveries per preference. Returns the preferences
+    /// at least one summary reached.
+    pub async fn send(
+        self,
+        db: &DatabaseConnection,
+        http: &Arc<Http>,
+    ) -> Result<HashSet<i32>, DbErr> {
+        let mut delivered = HashSet::new();
+        if self.entries.is_empty() {
+            return Ok(delivered);
+        }
+
+        let product_ids: Vec<i32> = self.entries.iter().map(|entry| entry.product_id).collect();
+        let products: HashMap<i32, products::Model> = products::Entity::find()
+            .filter(products::Column::ProductId.is_in(product_ids))
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|product| (product.product_id, product))
+            .collect();
+
+        for (recipient, indexes) in &self.recipients {
+            let mut entries: Vec<(&Entry, &products::Model)> = indexes
+                .iter()
+                .map(|&index| &self.entries[index])
+                .filter_map(|entry| Some((entry, products.get(&entry.product_id)?)))
+                .collect();
+            if entries.is_empty() {
+                continue;
+            }
+            // Biggest drop first, products without an earlier price last
+            entries.sort_by_key(|(entry, product)| {
+                let change = entry.change_percent(product);
+                (change.is_none(), change)
+            });
+
+            let result = self.deliver(db, http, recipient, &entries).await;
+            if let Err(e) = &result {
+                tracing::warn!("Failed to send sale summary: {}", e);
+            }
+            for (entry, _) in &entries {
+                record_attempt(
+                    db,
+                    entry.preference_id,
+                    &entry.alerts,
+                    recipient.channel_name(),
+                    &result,
+                )
+                .await?;
+                if result.is_ok() {
+                    delivered.insert(entry.preference_id);
+                }
+            }
+        }
+        Ok(delivered)
+    }
+
+    async fn deliver(
+        &self,
+        db: &DatabaseConnection,
+        http: &Arc<Http>,
+        recipient: &Recipient,
+        entries: &[(&Entry, &products::Model)],
+    ) -> Result<Option<i32>, String> {
+        let lines = || {
+            entries
+                .iter()
+                .map(|(entry, product)| {
+                    let price = match (entry.previous_price, entry.change_percent(product)) {
+                        (Some(previous), Some(change)) if change.is_sign_negative() => format!(
+                            "~~₹{}~~ → **₹{}** (−{}%)",
+                            previous,
+                            product.current_price,
+                            (-change).round_dp(0)
+                        ),
+                        _ => format!("**₹{}**", product.current_price),
+                    };
+                    format!(
+                        "[{}]({}) {}: {}",
+                        product_display_name(product),
+                        product_url(product),
+                        price,
+                        messages(entry).join("; ")
+                    )
+                })
+                .collect()
+        };
+
+        match recipient {
+            Recipient::Dm(user_id) => {
+                let message = CreateMessage::new().embed(summary_embed(
+                    "🎉 Sale on Myntra",
+                    "Products you track just dropped:",
+                    lines(),
+                ));
+                let dm = UserId::new(*user_id as u64)
+                    .create_dm_channel(http)
+                    .await
+                    .map_err(|e| e.to_string())?;
+                dm.send_message(http, message)
+                    .await
+                    .map_err(|e| e.to_string())?;
+                Ok(None)
+            }
+            Recipient::Channel(channel_id) => {
+                let message = CreateMessage::new().embed(summary_embed(
+                    "🎉 Sale on Myntra",
+                    "Products tracked here just dropped:",
+                    lines(),
+                ));
+                ChannelId::new(*channel_id as u64)
+                    .send_message(http, message)
+                    .await
+                    .map_err(|e| e.to_string())?;
+                Ok(None)
+            }
+            Recipient::Email(email) => {
+                let rows = entries
+                    .iter()
+                    .map(|(entry, product)| DigestRow {
+                        product_name: product_display_name(product),
+                        product_url: product_url(product),
+                        current_price: product.current_price,
+                        previous_price: entry.previous_price,
+                        alerts: messages(entry),
+                        manage_url: LinkConfig::get().manage_url(entry.preference_id),
+                    })
+                    .collect();
+                DigestEmail::new(email.clone(), None, rows)
+                    .heading(
+                        "Sale on Myntra: your tracked products dropped".to_string(),
+                        "This one summary replaces the separate alerts for these drops."
+                            .to_string(),
+                    )
+                    .queue(db)
+                    .await
+                    .map(Some)
+            }
+        }
+    }
+}
+
+fn messages(entry: &Entry) -> Vec<String> {
+    entry
+        .alerts
+        .iter()
+        .map(|alert| alert.message.clone())
+        .collect()
+}
@@ -1,19 +1,31 @@
 use anyhow::Context as _;
 use config::email::EmailConfig;
+use config::links::LinkConfig;
+use config::quotas::QuotaConfig;
+use cron::digests::DigestWorker;
+use cron::history::{HistoryRetention, RetentionConfig};
 use cron::notifications::{self, NotificationManager};
+use cron::outbox::OutboxWorker;
 use events::self_role_assign::self_role_assign;
 use moderation::spam::SpamChecker;
 use moderation::violations::{ModAction, ViolationThresholds, ViolationsTracker};
+use scraper::browser::BrowserPool;
+use scraper::deals::DealAnnouncer;
 use scraper::price_scraper::PriceScraper;
+use scraper::rotation::Rotation;
+use scraper::sales::{SaleConfig, SaleDetector};
+use scraper::watchlists::WatchlistAnnouncer;
 use sea_orm::{Database, DatabaseConnection};
 use serenity::all::{
     ChannelId, Command, CreateInteractionResponse, CreateInteractionResponseMessage, GuildId,
     Interaction, Member, Message, MessageId, Reaction, ReactionType, Ready, Timestamp, User,
 };
 use serenity::async_trait;
+use serenity::http::Http;
 use serenity::prelude::*;
 use shuttle_runtime::SecretStore;
 use std::error::Error;
+use std::sync::Arc;
 use tokio::time::Instant;
 use tracing::{error, info};
 use moderation::punishments::punish_member;
@@ -24,6 +36,7 @@ mod events;
 mod moderation;
 mod scraper;
 mod utils;
+mod web;
 use std::time::Duration;
 struct Bot {
     secrets: SecretStore,
@@ -31,16 +44,18 @@ struct Bot {
     violations_tracker: ViolationsTracker,
     violation_threshold: ViolationThresholds,
     db: DatabaseConnection,
+    scraper: Arc<PriceScraper>,
 }
 
 impl Bot {
-    pub fn new(secrets: SecretStore, db: DatabaseConnection) -> Self {
+    pub fn new(secrets: SecretStore, db: DatabaseConnection, scraper: Arc<PriceScraper>) -> Self {
         Self {
             secrets,
             spam_checker: SpamChecker::new(),
             violations_tracker: ViolationsTracker::new(),
             violation_threshold: ViolationThresholds::default(),
             db,
+            scraper,
         }
     }
     async fn reaction_add_internal(
@@ -169,6 +184,24 @@ impl EventHandler for Bot {
     }
 
     async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
+        if let Interaction::Autocomplete(autocomplete) = &interaction {
+            if let "untrack" | "edittracker" = autocomplete.data.name.as_str() {
+                commands::scrape::trackers::autocomplete(&ctx, autocomplete, &self.db).await;
+            }
+            return;
+        }
+
+        if let Interaction::Component(component) = &interaction {
+            if component
+                .data
+                .custom_id
+                .starts_with(scraper::deals::TRACK_BUTTON_PREFIX)
+            {
+                commands::scrape::deals::track_button(&ctx, component, &self.db).await;
+                return;
+            }
+        }
+
         if let Interaction::Command(command) = interaction {
             // println!("Received command interaction: {command:#?}");
 
@@ -243,17 +276,7 @@ impl EventHandler for Bot {
                     )
                     .await
                 }
-                "myntra" => {
-                    let response =
-                        commands::scrape::myntra::myntra_add(&command.data.options(), &self.db)
-                            .await
-                            .unwrap();
-                    let message = CreateInteractionResponseMessage::new().content(response);
-                    let builder = CreateInteractionResponse::Message(message);
-                    if let Err(why) = command.create_response(&ctx.http, builder).await {
-                        error!("Cannot respond to myntra_add command: {why}");
-                    }
-                }
+                "myntra" => commands::scrape::myntra::myntra_add(&ctx, &command, &self.db).await,
                 "cargocut" => {
                     // Handle URL shortening command
 
@@ -288,6 +311,41 @@ impl EventHandler for Bot {
                 let response_content = commands::serverstats::run(&ctx, &command).await;
                 utils::util::create_response(&ctx, &command, response_content).await;
             }
+                "pricehistory" => {
+                    commands::scrape::price_history::run(&ctx, &command, &self.db).await
+                }
+                "mytrackers" => {
+                    let response = commands::scrape::trackers::list(&command, &self.db).await;
+                    // Trackers show the emails their alerts go to
+                    utils::util::create_ephemeral_response(&ctx, &command, response).await;
+                }
+                "untrack" => {
+                    let response = commands::scrape::trackers::untrack(&command, &self.db).await;
+                    utils::util::create_ephemeral_response(&ctx, &command, response).await;
+                }
+                "edittracker" => {
+                    let response = commands::scrape::trackers::edit(&command, &self.db).await;
+                    utils::util::create_ephemeral_response(&ctx, &command, response).await;
+                }
+                "verifyemail" => {
+                    let response = commands::scrape::verify_email::run(&command, &self.db).await;
+                    utils::util::create_ephemeral_response(&ctx, &command, response).await;
+                }
+                "deals" => {
+                    let response = commands::scrape::deals::run(&command, &self.db).await;
+                    utils::util::create_response(&ctx, &command, response).await;
+                }
+                "watchlist" => commands::scrape::watchlist::run(&ctx, &command, &self.db).await,
+                "emaildigest" => {
+                    let response = commands::scrape::digest::run(&command, &self.db).await;
+                    utils::util::create_response(&ctx, &command, response).await;
+                }
+                "scraper" => commands::scraper::run(&ctx, &command, &self.db, &self.scraper).await,
+                "outbox" => {
+                    let response = commands::outbox::run(&command.data.options(), &self.db).await;
+                    // Failed emails are listed with their recipients
+                    utils::util::create_ephemeral_response(&ctx, &command, response).await;
+                }
                 "poll" => {
                     let response = commands::vote::run(&ctx, &command).await;
                     utils::util::create_response(&ctx, &command, response).await;
@@ -331,6 +389,14 @@ impl EventHandler for Bot {
                 &ctx.http,
                 vec![
                     commands::scrape::myntra::register_add(),
+                    commands::scrape::verify_email::register(),
+                    commands::scrape::trackers::register_list(),
+                    commands::scrape::trackers::register_untrack(),
+                    commands::scrape::trackers::register_edit(),
+                    commands::scrape::price_history::register(),
+                    commands::scrape::digest::register(),
+                    commands::scrape::deals::register(),
+                    commands::scrape::watchlist::register(),
                     commands::ping::register(),
                     commands::id::register(),
                     commands::welcome_message::register(),
@@ -341,6 +407,8 @@ impl EventHandler for Bot {
                     commands::youtube::yt_dlp::register_youtube(),
                     commands::serverstats::register(),
                     commands::vote::register(),
+                    commands::outbox::register(),
+                    commands::scraper::register(),
                 ],
             )
             .await;
@@ -370,17 +438,45 @@ async fn serenity(
         .expect("could not connect");
 
     EmailConfig::init(&secrets).expect("Could not initialize email config");
+    LinkConfig::init(&secrets).expect("Could not initialize link config");
+    QuotaConfig::init(&secrets).expect("Could not initialize tracker quotas");
+    Rotation::init(&secrets).expect("Could not initialize scraper rotation");
+    BrowserPool::init(&secrets).expect("Could not initialize browser pool");
+    BrowserPool::get().start().await;
 
-    let scraper = PriceScraper::new(db.clone());
-    tokio::spawn(async move { scraper.start_scraping().await });
+    let http = Arc::new(Http::new(&token));
+    let scraper = Arc::new(
+        PriceScraper::new(db.clone())
+            .announce_deals(DealAnnouncer::new(http.clone()))
+            .announce_watchlists(WatchlistAnnouncer::new(http.clone()))
+            .detect_sales(SaleDetector::new(
+                SaleConfig::from_secrets(&secrets).expect("Invalid sale detection settings"),
+                http.clone(),
+            )),
+    );
+    let hourly = scraper.clone();
+    tokio::spawn(async move { hourly.start_scraping().await });
 
     let mut manager = NotificationManager::new(db.clone());
-    manager.register_handler(notifications::MyntraHandler);
+    manager.register_handler(notifications::MyntraHandler::new(http));
     manager.start().await;
 
+    OutboxWorker::from_secrets(db.clone(), &secrets)
+        .expect("Invalid email outbox settings")
+        .start()
+        .await;
+    DigestWorker::new(db.clone()).start().await;
+    HistoryRetention::new(
+        db.clone(),
+        RetentionConfig::from_secrets(&secrets).expect("Invalid price history retention"),
+    )
+    .start()
+    .await;
+    web::start(db.clone()).await;
+
     // Pass secrets to Bot constructor
     let client = Client::builder(&token, intents)
-        .event_handler(Bot::new(secrets, db))
+        .event_handler(Bot::new(secrets, db, scraper))
         .await
         .expect("Err creating client");
 
@@ -0,0 +1,354 @@
+use headless_chrome::{Browser, LaunchOptions, LaunchOptionsBuilder, Tab};
+use once_cell::sync::OnceCell;
+use serde::Serialize;
+use shuttle_runtime::SecretStore;
+use std::ffi::OsStr;
+use std::ops::Deref;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Instant;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::time::{interval, Duration};
+
+use super::rotation::{Outcome, Rotation};
+
+static BROWSER_POOL: OnceCell<BrowserPool> = OnceCell::new();
+
+const DEFAULT_POOL_SIZE: usize = 1;
+const DEFAULT_MAX_TABS: usize = 2;
+const DEFAULT_MAX_PAGE_LOADS: u64 = 500;
+const DEFAULT_IDLE_SECS: u64 = 600;
+const MAINTENANCE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Shared headless Chrome instances for every scrape, so concurrent `/myntra` adds and the
+/// hourly scraper reuse a few browsers instead of launching one each.
+///
+/// Configured with `BROWSER_POOL_SIZE` (browsers), `BROWSER_MAX_TABS` (tabs open at once
+/// across the pool), `BROWSER_MAX_PAGE_LOADS` (page loads before a browser is replaced)
+/// and `BROWSER_IDLE_SECS` (how long an unused browser is kept running). Each browser
+/// goes through a proxy from [`Rotation`] when proxies are configured, and is replaced
+/// with one on another proxy once it runs into a block page.
+pub struct BrowserPool {
+    slots: Vec<Mutex<Slot>>,
+    tabs: Arc<Semaphore>,
+    max_tabs: usize,
+    max_page_loads: u64,
+    idle_timeout: Duration,
+    launches: AtomicU64,
+    restarts: AtomicU64,
+    page_loads: AtomicU64,
+    blocked_pages: AtomicU64,
+    failures: AtomicU64,
+}
+
+#[derive(Default)]
+struct Slot {
+    browser: Option<Browser>,
+    proxy: Option<usize>,
+    /// Blocked or failing; replaced as soon as no tab uses it.
+    burned: bool,
+    leases: usize,
+    page_loads: u64,
+    last_used: Option<Instant>,
+}
+
+/// Health figures for logs and the `/health/browsers` endpoint.
+#[derive(Serialize)]
+pub struct PoolMetrics {
+    pub browsers: usize,
+    pub browsers_running: usize,
+    pub tabs_in_use: usize,
+    pub max_tabs: usize,
+    pub launches: u64,
+    pub restarts: u64,
+    pub page_loads: u64,
+    pub blocked_pages: u64,
+    pub failures: u64,
+}
+
+impl BrowserPool {
+    pub fn init(secrets: &SecretStore) -> Result<(), Box<dyn std::error::Error>> {
+        let pool_size = match secrets.get("BROWSER_POOL_SIZE") {
+            Some(size) => size.parse()?,
+            None => DEFAULT_POOL_SIZE,
+        };
+        let max_tabs = match secrets.get("BROWSER_MAX_TABS") {
+            Some(tabs) => tabs.parse()?,
+            None => DEFAULT_MAX_TABS,
+        };
+        let max_page_loads = match secrets.get("BROWSER_MAX_PAGE_LOADS") {
+            Some(loads) => loads.parse()?,
+            None => DEFAULT_MAX_PAGE_LOADS,
+        };
+        let idle_secs = match secrets.get("BROWSER_IDLE_SECS") {
+            Some(secs) => secs.parse()?,
+            None => DEFAULT_IDLE_SECS,
+        };
+        if pool_size == 0 || max_tabs == 0 {
+            return Err("BROWSER_POOL_SIZE and BROWSER_MAX_TABS must be at least 1".into());
+        }
+
+        BROWSER_POOL
+            .set(Self {
+                slots: (0..pool_size).map(|_| Mutex::default()).collect(),
+                tabs: Arc::new(Semaphore::new(max_tabs)),
+                max_tabs,
+                max_page_loads,
+                idle_timeout: Duration::from_secs(idle_secs),
+                launches: AtomicU64::new(0),
+                restarts: AtomicU64::new(0),
+                page_loads: AtomicU64::new(0),
+                blocked_pages: AtomicU64::new(0),
+                failures: AtomicU64::new(0),
+            })
+            .map_err(|_| "BrowserPool already initialized")?;
+        Ok(())
+    }
+
+    pub fn get() -> &'static BrowserPool {
+        BROWSER_POOL.get().expect("BrowserPool not initialized")
+    }
+
+    /// Shuts down idle browsers and replaces ones that leaked tabs, once a minute.
+    pub async fn start(&'static self) {
+        let mut interval = interval(MAINTENANCE_INTERVAL);
+
+        tokio::spawn(async move {
+            loop {
+                interval.tick().await;
+                // Stopping a browser waits for its Chrome process to exit
+                if let Err(e) = tokio::task::spawn_blocking(|| self.maintain()).await {
+                    tracing::error!("Browser maintenance failed: {}", e);
+                }
+            }
+        });
+    }
+
+    /// Waits for a free tab slot and opens a tab in the least busy browser that has not
+    /// been blocked, launching or restarting it first when needed. The tab is closed when
+    /// the returned guard drops.
+    pub async fn tab(&'static self) -> Result<PooledTab, String> {
+        let permit = self
+            .tabs
+            .clone()
+            .acquire_owned()
+            .await
+            .map_err(|e| e.to_string())?;
+
+        // Launching Chrome and opening a tab wait on the browser while the slot is locked,
+        // so they run on the blocking pool rather than an async worker
+        tokio::task::spawn_blocking(move || self.open_tab(permit))
+            .await
+            .map_err(|e| e.to_string())?
+    }
+
+    fn open_tab(&self, permit: OwnedSemaphorePermit) -> Result<PooledTab, String> {
+        let index = (0..self.slots.len())
+            .min_by_key(|index| {
+                let slot = self.slot(*index);
+                (slot.burned, slot.leases)
+            })
+            .unwrap_or_default();
+        let mut slot = self.slot(index);
+        let browser = self.healthy_browser(&mut slot)?;
+
+        match browser
+            .new_tab()
+            .and_then(|tab| authenticate(tab, slot.proxy))
+        {
+            Ok(tab) => {
+                slot.leases += 1;
+                Ok(PooledTab {
+                    tab,
+                    slot: index,
+                    proxy: slot.proxy,
+                    _permit: permit,
+                })
+            }
+            Err(e) => {
+                // A browser that cannot open tabs is restarted on the next checkout
+                self.failures.fetch_add(1, Ordering::Relaxed);
+                slot.browser = None;
+                Err(format!("Could not open a browser tab: {}", e))
+            }
+        }
+    }
+
+    pub fn metrics(&self) -> PoolMetrics {
+        let (mut running, mut tabs_in_use) = (0, 0);
+        for index in 0..self.slots.len() {
+            let slot = self.slot(index);
+            running += usize::from(slot.browser.is_some());
+            tabs_in_use += slot.leases;
+        }
+        PoolMetrics {
+            browsers: self.slots.len(),
+            browsers_running: running,
+            tabs_in_use,
+            max_tabs: self.max_tabs,
+            launches: self.launches.load(Ordering::Relaxed),
+            restarts: self.restarts.load(Ordering::Relaxed),
+            page_loads: self.page_loads.load(Ordering::Relaxed),
+            blocked_pages: self.blocked_pages.load(Ordering::Relaxed),
+            failures: self.failures.load(Ordering::Relaxed),
+        }
+    }
+
+    // A panic while holding the lock leaves the slot usable; the health check catches
+    // a browser left in a bad state
+    fn slot(&self, index: usize) -> MutexGuard<'_, Slot> {
+        self.slots[index]
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn healthy_browser(&self, slot: &mut Slot) -> Result<Browser, String> {
+        if let Some(browser) = &slot.browser {
+            let worn_out =
+                slot.leases == 0 && (slot.burned || slot.page_loads >= self.max_page_loads);
+            if !worn_out && browser.get_version().is_ok() {
+                return Ok(browser.clone());
+            }
+            if !worn_out {
+                self.failures.fetch_add(1, Ordering::Relaxed);
+                tracing::warn!("Browser stopped responding, restarting it");
+            }
+            self.restarts.fetch_add(1, Ordering::Relaxed);
+            // Dropping the last handle kills the Chrome process
+            slot.browser = None;
+        }
+
+        let rotation = Rotation::get();
+        let proxy = rotation.pick_proxy();
+        let server = proxy.map(|index| rotation.proxy(index).server.as_str());
+        let browser = launch_options(server).and_then(Browser::new).map_err(|e| {
+            self.failures.fetch_add(1, Ordering::Relaxed);
+            format!("Could not launch Chrome: {}", e)
+        })?;
+        if let Some(server) = server {
+            tracing::info!("Launched browser through proxy {}", server);
+        }
+        self.launches.fetch_add(1, Ordering::Relaxed);
+        slot.page_loads = 0;
+        slot.proxy = proxy;
+        slot.burned = false;
+        slot.browser = Some(browser.clone());
+        Ok(browser)
+    }
+
+    fn maintain(&self) {
+        for index in 0..self.slots.len() {
+            let mut slot = self.slot(index);
+            if slot.leases > 0 {
+                continue;
+            }
+            let Some(browser) = &slot.browser else {
+                continue;
+            };
+
+            let idle = slot
+                .last_used
+                .is_none_or(|last_used| last_used.elapsed() >= self.idle_timeout);
+            // Only the initial blank tab should be left once every lease is returned
+            let leaked = browser
+                .get_tabs()
+                .lock()
+                .map(|tabs| tabs.len() > 1)
+                .unwrap_or(true);
+
+            if leaked {
+                tracing::warn!("Browser {} leaked tabs, restarting it", index);
+                self.restarts.fetch_add(1, Ordering::Relaxed);
+                slot.browser = None;
+            } else if idle {
+                tracing::info!("Shutting down idle browser {}", index);
+                slot.browser = None;
+            }
+        }
+    }
+}
+
+// The user agent is set per tab from a rotation profile
+fn launch_options(proxy_server: Option<&str>) -> Result<LaunchOptions<'_>, anyhow::Error> {
+    LaunchOptionsBuilder::default()
+        .args(vec![
+            OsStr::new("--disable-blink-features=AutomationControlled"),
+            OsStr::new("--disable-gpu"),
+            OsStr::new("--no-sandbox"),
+            OsStr::new("--window-size=1920,1080"),
+        ])
+        .proxy_server(proxy_server)
+        .headless(true)
+        // The pool decides when a browser goes away, not the connection's idle timer
+        .idle_browser_timeout(std::time::Duration::from_secs(24 * 3600))
+        .build()
+        .map_err(anyhow::Error::msg)
+}
+
+// Chrome takes no credentials in `--proxy-server`, so they are answered per tab
+fn authenticate(tab: Arc<Tab>, proxy: Option<usize>) -> Result<Arc<Tab>, anyhow::Error> {
+    let Some(proxy) = proxy.map(|index| Rotation::get().proxy(index)) else {
+        return Ok(tab);
+    };
+    if proxy.username.is_some() {
+        tab.enable_fetch(None, Some(true))?;
+        tab.authenticate(proxy.username.clone(), proxy.password.clone())?;
+    }
+    Ok(tab)
+}
+
+/// A tab leased from the pool; derefs to the `headless_chrome` tab.
+pub struct PooledTab {
+    tab: Arc<Tab>,
+    slot: usize,
+    proxy: Option<usize>,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl PooledTab {
+    /// Navigates and waits for the page, counting the load towards the browser's recycling.
+    pub fn open(&self, url: &str) -> Result<(), anyhow::Error> {
+        let pool = BrowserPool::get();
+        pool.page_loads.fetch_add(1, Ordering::Relaxed);
+        pool.slot(self.slot).page_loads += 1;
+        self.tab.navigate_to(url)?;
+        self.tab.wait_until_navigated()?;
+        Ok(())
+    }
+
+    /// Records how the page load went against the browser's proxy. A blocked browser,
+    /// or a failing one behind a proxy, is replaced once its tabs are closed.
+    pub fn report(&self, outcome: Outcome) {
+        let pool = BrowserPool::get();
+        if let Some(proxy) = self.proxy {
+            Rotation::get().record(proxy, outcome);
+        }
+        if outcome == Outcome::Blocked {
+            pool.blocked_pages.fetch_add(1, Ordering::Relaxed);
+        }
+        if outcome == Outcome::Blocked || (outcome == Outcome::Failed && self.proxy.is_some()) {
+            pool.slot(self.slot).burned = true;
+        }
+    }
+}
+
+impl Deref for PooledTab {
+    type Target = Tab;
+
+    fn deref(&self) -> &Tab {
+        &self.tab
+    }
+}
+
+// Closing waits for Chrome to answer, so drop tabs on the blocking pool like the rest of
+// the calls on them
+impl Drop for PooledTab {
+    fn drop(&mut self) {
+        if let Err(e) = self.tab.close(false) {
+            tracing::warn!("Could not close browser tab: {}", e);
+        }
+        let mut slot = BrowserPool::get().slot(self.slot);
+        slot.leases = slot.leases.saturating_sub(1);
+        slot.last_used = Some(Instant::now());
+    }
+}
@@ -0,0 +1,91 @@
+use ::entity::{deal_channels, notification_preferences, products};
+use sea_orm::prelude::Decimal;
+use sea_orm::sea_query::Query;
+use sea_orm::*;
+use serenity::all::{ChannelId, CreateActionRow, CreateButton, CreateMessage, Http};
+use std::sync::Arc;
+
+use crate::cron::notifications::percent_change;
+use crate::utils::embeds::deal_embed;
+use crate::utils::util::product_url;
+
+/// Custom id prefix of the "Track this" button on deal posts, followed by the product id.
+pub const TRACK_BUTTON_PREFIX: &str = "deal_track:";
+
+/// Posts big price drops and all-time lows to the deals channel of every guild that
+/// turned the feature on with `/deals` and has a member tracking the product.
+pub struct DealAnnouncer {
+    http: Arc<Http>,
+}
+
+/// A product row before and after one scrape updated it.
+pub struct PriceChange {
+    pub before: products::Model,
+    pub after: products::Model,
+}
+
+impl DealAnnouncer {
+    pub fn new(http: Arc<Http>) -> Self {
+        Self { http }
+    }
+
+    pub async fn announce(&self, db: &DatabaseConnection, change: &PriceChange) {
+        let (before, after) = (&change.before, &change.after);
+        if before.current_price.is_zero() || after.current_price >= before.current_price {
+            return;
+        }
+        let drop = -percent_change(before.current_price, after.current_price);
+        let all_time_low = after.current_price < before.lowest_price;
+
+        // Products tracked elsewhere are none of this guild's business
+        let tracked_here = Query::select()
+            .column(notification_preferences::Column::GuildId)
+            .from(notification_preferences::Entity)
+            .and_where(notification_preferences::Column::ProductId.eq(after.product_id))
+            .and_where(notification_preferences::Column::GuildId.is_not_null())
+            .to_owned();
+        let channels = deal_channels::Entity::find()
+            .filter(deal_channels::Column::GuildId.in_subquery(tracked_here))
+            .all(db)
+            .await;
+        let channels = match channels {
+            Ok(channels) => channels,
+            Err(e) => {
+                tracing::error!("Failed to load deal channels: {}", e);
+                return;
+            }
+        };
+
+        for channel in channels {
+            let big_drop = drop >= Decimal::from(channel.drop_percent);
+            let headline = match (big_drop, all_time_low && channel.announce_lows) {
+                (true, true) => format!("🔥 Down {}% to an all-time low", drop.round_dp(0)),
+                (true, false) => format!("🔥 Down {}%", drop.round_dp(0)),
+                (false, true) => format!("📉 New all-time low, down {}%", drop.round_dp(0)),
+                (false, false) => continue,
+            };
+
+            let buttons = CreateActionRow::Buttons(vec![
+                CreateButton::new(format!("{}{}", TRACK_BUTTON_PREFIX, after.product_id))
+                    .label("Track this")
+                    .emoji('🔔'),
+                CreateButton::new_link(product_url(after)).label("View on Myntra"),
+            ]);
+            let message = CreateMessage::new()
+                .embed(deal_embed(after, &headline, before.current_price))
+                .components(vec![buttons]);
+
+            if let Err(e) = ChannelId::new(channel.channel_id as u64)
+                .send_message(&self.http, message)
+                .await
+            {
+                tracing::error!(
+                    "Failed to post deal for product {} in guild {}: {}",
+                    after.product_id,
+                    channel.guild_id,
+                    e
+                );
+            }
+        }
+    }
+}
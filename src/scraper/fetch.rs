@@ -0,0 +1,140 @@
+use async_trait::async_trait;
+use rand::Rng;
+use std::collections::HashMap;
+use std::time::Duration;
+
+use super::browser::{BrowserPool, PooledTab};
+use super::myntra::looks_blocked;
+use super::rotation::{HeaderProfile, Outcome, Rotation};
+
+// Tries per product; a blocked try is repeated in another browser, behind another proxy
+// when there are several
+const MAX_ATTEMPTS: usize = 3;
+
+/// Where product pages come from. Production goes through headless Chrome; tests point
+/// an [`HttpFetcher`] at a local server with saved pages.
+#[async_trait]
+pub trait PageFetcher: Send + Sync {
+    /// HTML of the product page of `product_id`.
+    async fn fetch(&self, product_id: i32) -> Result<String, String>;
+}
+
+/// Loads pages on myntra.com in a pooled Chrome tab, looking like a regular browser with
+/// a header profile from [`Rotation`]. Block pages are retried in a fresh browser; the
+/// last one is returned when every try is blocked, for the parser to flag.
+pub struct ChromeFetcher;
+
+#[async_trait]
+impl PageFetcher for ChromeFetcher {
+    async fn fetch(&self, product_id: i32) -> Result<String, String> {
+        let mut last = Err("No attempt made".to_string());
+        for attempt in 1..=MAX_ATTEMPTS {
+            let delay = rand::thread_rng().gen_range(2000..5000);
+            tokio::time::sleep(Duration::from_millis(delay)).await;
+
+            let tab = BrowserPool::get().tab().await?;
+            // Every call on the tab waits for Chrome, closing it on drop included
+            let loaded = tokio::task::spawn_blocking(move || {
+                let page = load_product_page(&tab, product_id, Rotation::get().profile());
+                let outcome = match &page {
+                    Ok(page) if looks_blocked(page) => Outcome::Blocked,
+                    Ok(_) => Outcome::Success,
+                    Err(_) => Outcome::Failed,
+                };
+                tab.report(outcome);
+                page.map(|page| (page, outcome))
+            })
+            .await
+            .map_err(|e| e.to_string())?;
+
+            match loaded {
+                Ok((page, Outcome::Blocked)) => {
+                    tracing::warn!(
+                        "Product {} got a block page (attempt {}/{})",
+                        product_id,
+                        attempt,
+                        MAX_ATTEMPTS
+                    );
+                    last = Ok(page);
+                }
+                Ok((page, _)) => return Ok(page),
+                Err(e) => last = Err(e.to_string()),
+            }
+        }
+        last
+    }
+}
+
+fn load_product_page(
+    tab: &PooledTab,
+    url: i32,
+    profile: &HeaderProfile,
+) -> Result<String, anyhow::Error> {
+    tab.set_user_agent(
+        &profile.user_agent,
+        Some(&profile.accept_language),
+        Some(&profile.platform),
+    )?;
+    tab.set_extra_http_headers(
+        profile
+            .headers
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect::<HashMap<_, _>>(),
+    )?;
+
+    tab.evaluate(
+        r#"Object.defineProperty(navigator, 'webdriver', { get: () => false });"#,
+        true,
+    )?;
+
+    let url2 = format!("https://www.myntra.com/{}", url);
+    tab.open(&url2)?;
+
+    tab.evaluate(
+        r#"
+        function simulateHumanBehavior() {
+            window.scrollTo(0, Math.random() * 100);
+            setTimeout(() => {
+                window.scrollTo(0, Math.random() * 500);
+            }, 1000);
+        }
+        simulateHumanBehavior();
+        "#,
+        true,
+    )?;
+
+    tab.get_content()
+}
+
+/// Plain GET of `<base_url>/<product_id>`, for a local fixture server.
+#[cfg(test)]
+pub struct HttpFetcher {
+    base_url: String,
+    client: reqwest::Client,
+}
+
+#[cfg(test)]
+impl HttpFetcher {
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+#[async_trait]
+impl PageFetcher for HttpFetcher {
+    async fn fetch(&self, product_id: i32) -> Result<String, String> {
+        self.client
+            .get(format!("{}/{}", self.base_url, product_id))
+            .send()
+            .await
+            .map_err(|e| e.to_string())?
+            .text()
+            .await
+            .map_err(|e| e.to_string())
+    }
+}
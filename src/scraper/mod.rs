@@ -1,2 +1,12 @@
+pub mod browser;
+pub mod deals;
+pub mod fetch;
 pub mod myntra;
 pub mod price_scraper;
+pub mod rotation;
+pub mod sales;
+pub mod status;
+pub mod watchlists;
+
+#[cfg(test)]
+mod tests;
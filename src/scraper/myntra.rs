@@ -1,99 +1,246 @@
-use headless_chrome::{Browser, LaunchOptionsBuilder};
-use rand::Rng;
+use once_cell::sync::Lazy;
+use regex::Regex;
 use scraper::{Html, Selector};
-use std::{ffi::OsStr, thread, time::Duration};
+use sea_orm::prelude::Decimal;
+use std::str::FromStr;
 
-pub async fn scrape_products(urls: Vec<i32>) -> Result<Vec<String>, Box<dyn std::error::Error>> {
-    let mut prices = Vec::new();
-    let options = LaunchOptionsBuilder::default()
-        .args(vec![
-            OsStr::new("--disable-blink-features=AutomationControlled"),
-            OsStr::new("--disable-gpu"),
-            OsStr::new("--no-sandbox"),
-            OsStr::new("--window-size=1920,1080"),
-            OsStr::new("--user-agent=Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36")
-        ])
-        .headless(true)
-        .build()?;
-
-    let browser = Browser::new(options)?;
-    let tab = browser.new_tab()?;
+use super::fetch::{ChromeFetcher, PageFetcher};
+
+/// Availability of a single size button on the product page.
+#[derive(Debug, Clone)]
+pub struct SizeStock {
+    pub label: String,
+    pub in_stock: bool,
+}
+
+/// Everything we read off a product page in one scrape.
+#[derive(Debug, Clone)]
+pub struct ProductSnapshot {
+    pub price: Decimal,
+    pub mrp: Option<Decimal>,
+    pub discount_percent: Option<i32>,
+    pub in_stock: bool,
+    pub sizes: Vec<SizeStock>,
+    pub name: Option<String>,
+    pub brand: Option<String>,
+    pub image_url: Option<String>,
+    pub canonical_url: Option<String>,
+    /// Bot protection answered instead of Myntra; nothing on the page is about the product.
+    pub blocked: bool,
+}
+
+static PRODUCT_URL: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^https?://(?:www\.|m\.)?myntra\.com/(?:[^?#]*/)?(\d{4,10})(?:/buy)?/?(?:[?#].*)?$")
+        .unwrap()
+});
+static SHARE_LINK: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^https?://(?:www\.)?myntr\.it/[A-Za-z0-9]+/?$").unwrap());
+
+/// Turns whatever the user pasted (a bare id, a product URL or a myntr.it share link)
+/// into a Myntra product id.
+pub async fn resolve_product_id(input: &str) -> Result<i32, String> {
+    let input = input.trim();
+    if let Ok(id) = input.parse::<i32>() {
+        return if id > 0 {
+            Ok(id)
+        } else {
+            Err("Product ids are positive numbers.".to_string())
+        };
+    }
+
+    if let Some(id) = product_id_from_url(input) {
+        return Ok(id);
+    }
+
+    if SHARE_LINK.is_match(input) {
+        // Share links are plain redirects to the product page
+        let response = reqwest::get(input)
+            .await
+            .map_err(|e| format!("Could not open the share link: {}", e))?;
+        return product_id_from_url(response.url().as_str())
+            .ok_or_else(|| "That share link does not point to a Myntra product.".to_string());
+    }
 
+    Err("Please paste a Myntra product link, e.g. https://www.myntra.com/tshirts/roadster/.../1234567/buy".to_string())
+}
+
+pub fn product_id_from_url(url: &str) -> Option<i32> {
+    PRODUCT_URL
+        .captures(url)
+        .and_then(|captures| captures[1].parse().ok())
+}
+
+/// Scrapes the products through the shared headless Chrome pool, one snapshot per id.
+pub async fn scrape_products(
+    urls: Vec<i32>,
+) -> Result<Vec<ProductSnapshot>, Box<dyn std::error::Error>> {
+    scrape_with(&ChromeFetcher, urls).await
+}
+
+/// Fetches and parses each product page in order. Fetching is kept apart from parsing so
+/// tests can serve saved pages instead of hitting Myntra.
+pub async fn scrape_with(
+    fetcher: &dyn PageFetcher,
+    urls: Vec<i32>,
+) -> Result<Vec<ProductSnapshot>, Box<dyn std::error::Error>> {
+    let mut prices = Vec::new();
     for url in urls {
-        let delay = rand::thread_rng().gen_range(2000..5000);
-        thread::sleep(Duration::from_millis(delay));
-
-        tab.evaluate(
-            r#"Object.defineProperty(navigator, 'webdriver', { get: () => false });"#,
-            true,
-        )?;
-
-        tab.evaluate(&format!(r#"
-            const originalFetch = window.fetch;
-            window.fetch = function(input, init) {{
-                const headers = {{
-                    'Accept': 'text/html,application/xhtml+xml,application/xml;q=0.9,image/webp,image/apng,*/*;q=0.8',
-                    'Accept-Language': 'en-US,en;q=0.9',
-                    'Cache-Control': 'max-age=0',
-                    'Connection': 'keep-alive',
-                    'Sec-Ch-Ua': '"Not_A Brand";v="8", "Chromium";v="120"',
-                    'Sec-Ch-Ua-Mobile': '?0',
-                    'Sec-Ch-Ua-Platform': '"Windows"',
-                    'Upgrade-Insecure-Requests': '1',
-                    'User-Agent': 'Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36',
-                    'Sec-Fetch-Dest': 'document',
-                    'Sec-Fetch-Mode': 'navigate',
-                    'Sec-Fetch-Site': 'same-origin',
-                    'Sec-Fetch-User': '?1'
-                }};
-                init = init || {{}};
-                init.headers = {{ ...headers, ...init.headers }};
-                return originalFetch(input, init);
-            }};
-        "#), true)?;
-
-        let url2 = format!("https://www.myntra.com/{}", url);
-        tab.navigate_to(&url2)?;
-        tab.wait_until_navigated()?;
-
-        tab.evaluate(
-            r#"
-            function simulateHumanBehavior() {
-                window.scrollTo(0, Math.random() * 100);
-                setTimeout(() => {
-                    window.scrollTo(0, Math.random() * 500);
-                }, 1000);
-            }
-            simulateHumanBehavior();
-            "#,
-            true,
-        )?;
+        let page_content = fetcher.fetch(url).await?;
+        prices.push(parse_product_page(&page_content));
+    }
+    tracing::info!(
+        " the prices are {:?}",
+        prices.iter().map(|p| p.price).collect::<Vec<_>>()
+    );
 
-        let page_content = tab.get_content()?;
-        let document = Html::parse_document(&page_content);
-        let price_selector = Selector::parse("span.pdp-price").unwrap();
+    Ok(prices)
+}
 
-        let price = document
-            .select(&price_selector)
+/// Whether bot protection answered instead of a product page.
+pub fn looks_blocked(page_content: &str) -> bool {
+    is_block_page(&Html::parse_document(page_content))
+}
+
+// Akamai's "Access Denied" page and captcha challenges come back with a 200 as well
+fn is_block_page(document: &Html) -> bool {
+    let title_selector = Selector::parse("title").unwrap();
+    let captcha_selector =
+        Selector::parse(r#"#captcha, .g-recaptcha, .h-captcha, iframe[src*="captcha"]"#).unwrap();
+
+    document.select(&captcha_selector).next().is_some()
+        || document
+            .select(&title_selector)
             .next()
-            .and_then(|el| {
-                let price_text = el.text().collect::<String>();
-                tracing::info!(" the prices text {price_text:?}");
-                Some(
-                    price_text
-                        .trim()
-                        .replace("MRP₹", "")
-                        .replace("₹", "")
-                        .replace(',', "")
-                        .trim()
-                        .to_string(),
-                )
+            .is_some_and(|title| {
+                let title = title.text().collect::<String>().to_lowercase();
+                title.contains("access denied") || title.contains("are you a robot")
             })
-            .unwrap_or("0".to_string());
+}
+
+pub fn parse_product_page(page_content: &str) -> ProductSnapshot {
+    let document = Html::parse_document(page_content);
+    let price_selector = Selector::parse("span.pdp-price").unwrap();
+    let mrp_selector = Selector::parse("span.pdp-mrp").unwrap();
+    let discount_selector = Selector::parse("span.pdp-discount").unwrap();
+    let size_selector = Selector::parse("button.size-buttons-size-button").unwrap();
+    let size_label_selector = Selector::parse("p.size-buttons-unified-size").unwrap();
+    let out_of_stock_selector = Selector::parse("div.size-buttons-out-of-stock").unwrap();
+    let name_selector = Selector::parse("h1.pdp-name").unwrap();
+    let brand_selector = Selector::parse("h1.pdp-title").unwrap();
+    let image_selector = Selector::parse(r#"meta[property="og:image"]"#).unwrap();
+    let canonical_selector = Selector::parse(r#"link[rel="canonical"]"#).unwrap();
+    let og_url_selector = Selector::parse(r#"meta[property="og:url"]"#).unwrap();
+
+    let price = document
+        .select(&price_selector)
+        .next()
+        .and_then(|el| {
+            let price_text = el.text().collect::<String>();
+            tracing::info!(" the prices text {price_text:?}");
+            parse_rupees(&price_text)
+        })
+        .unwrap_or_default();
+
+    let mrp = document
+        .select(&mrp_selector)
+        .next()
+        .and_then(|el| parse_rupees(&el.text().collect::<String>()));
+
+    // Myntra shows "(40% OFF)"; flat "Rs. 500 OFF" discounts are derived from the MRP instead
+    let discount_percent = document
+        .select(&discount_selector)
+        .next()
+        .map(|el| el.text().collect::<String>())
+        .filter(|text| text.contains('%'))
+        .and_then(|text| {
+            text.chars()
+                .filter(|c| c.is_ascii_digit())
+                .collect::<String>()
+                .parse()
+                .ok()
+        })
+        .or_else(|| discount_from_mrp(price, mrp));
+
+    let sizes: Vec<SizeStock> = document
+        .select(&size_selector)
+        .filter_map(|button| {
+            let label = button
+                .select(&size_label_selector)
+                .next()
+                .map(|el| el.text().collect::<String>())
+                .unwrap_or_else(|| button.text().collect::<String>());
+            let label = label.trim().to_string();
+            if label.is_empty() {
+                return None;
+            }
+            let in_stock = !button
+                .value()
+                .classes()
+                .any(|class| class == "size-buttons-size-button-disabled");
+            Some(SizeStock { label, in_stock })
+        })
+        .collect();
+
+    let in_stock = if document.select(&out_of_stock_selector).next().is_some() {
+        false
+    } else if sizes.is_empty() {
+        !price.is_zero()
+    } else {
+        sizes.iter().any(|size| size.in_stock)
+    };
 
-        prices.push(price);
+    let text_of = |selector: &Selector| {
+        document
+            .select(selector)
+            .next()
+            .map(|el| el.text().collect::<String>().trim().to_string())
+            .filter(|text| !text.is_empty())
+    };
+    let attr_of = |selector: &Selector, attr: &str| {
+        document
+            .select(selector)
+            .next()
+            .and_then(|el| el.value().attr(attr))
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    };
+
+    let blocked = is_block_page(&document);
+
+    ProductSnapshot {
+        price,
+        mrp,
+        discount_percent,
+        in_stock,
+        sizes,
+        name: text_of(&name_selector),
+        brand: text_of(&brand_selector),
+        image_url: attr_of(&image_selector, "content"),
+        canonical_url: attr_of(&canonical_selector, "href")
+            .or_else(|| attr_of(&og_url_selector, "content")),
+        blocked,
     }
-    tracing::info!(" the prices are {prices:?}");
+}
 
-    Ok(prices)
+impl ProductSnapshot {
+    /// Removed or mistyped products render Myntra's error page, which has neither a price nor a title.
+    pub fn is_product_page(&self) -> bool {
+        !self.blocked && (!self.price.is_zero() || self.name.is_some())
+    }
+}
+
+fn parse_rupees(text: &str) -> Option<Decimal> {
+    let digits: String = text
+        .trim()
+        .replace("MRP", "")
+        .chars()
+        .filter(|c| c.is_ascii_digit() || *c == '.')
+        .collect();
+    Decimal::from_str(digits.trim_matches('.')).ok()
+}
+
+fn discount_from_mrp(price: Decimal, mrp: Option<Decimal>) -> Option<i32> {
+    let mrp = mrp.filter(|mrp| !mrp.is_zero() && *mrp > price)?;
+    let percent = (mrp - price) * Decimal::from(100) / mrp;
+    percent.trunc().to_string().parse().ok()
 }
@@ -1,76 +1,318 @@
-use super::myntra::scrape_products;
-use ::entity::{notification_preferences, price_history, products};
-use prelude::Decimal;
+use super::deals::{DealAnnouncer, PriceChange};
+use super::fetch::{ChromeFetcher, PageFetcher};
+use super::myntra::{parse_product_page, ProductSnapshot};
+use super::sales::SaleDetector;
+use super::status::{RunReport, ScrapeFailure, ScraperStatus};
+use super::watchlists::WatchlistAnnouncer;
+use ::entity::{
+    channel_watchlists, notification_preferences, price_history, product_sizes, products,
+};
+use chrono::Utc;
 use sea_orm::*;
-use std::str::FromStr;
 use std::sync::Arc;
+use std::time::Instant;
+
+const SCRAPE_INTERVAL_SECS: i64 = 3600;
 
 pub struct PriceScraper {
     db: Arc<DatabaseConnection>,
+    fetcher: Box<dyn PageFetcher>,
+    deals: Option<DealAnnouncer>,
+    watchlists: Option<WatchlistAnnouncer>,
+    sales: Option<SaleDetector>,
 }
 
 impl PriceScraper {
     pub fn new(db: DatabaseConnection) -> Self {
-        Self { db: Arc::new(db) }
+        Self::with_fetcher(db, Box::new(ChromeFetcher))
+    }
+
+    pub fn with_fetcher(db: DatabaseConnection, fetcher: Box<dyn PageFetcher>) -> Self {
+        Self {
+            db: Arc::new(db),
+            fetcher,
+            deals: None,
+            watchlists: None,
+            sales: None,
+        }
+    }
+
+    /// Posts big drops to the guilds' deals channels after each scrape.
+    pub fn announce_deals(mut self, deals: DealAnnouncer) -> Self {
+        self.deals = Some(deals);
+        self
+    }
+
+    /// Posts price events to the channels watching the product after each scrape.
+    pub fn announce_watchlists(mut self, watchlists: WatchlistAnnouncer) -> Self {
+        self.watchlists = Some(watchlists);
+        self
     }
 
+    /// Sends one sale summary instead of single deal posts when a run sees many products
+    /// drop at once.
+    pub fn detect_sales(mut self, sales: SaleDetector) -> Self {
+        self.sales = Some(sales);
+        self
+    }
+
+    /// Scrapes every hour unless an admin paused the loop with `/scraper pause`.
     pub async fn start_scraping(&self) {
+        let status = ScraperStatus::get();
+        loop {
+            if status.is_paused() {
+                tracing::info!("Scraper is paused, skipping this run");
+            } else {
+                self.scrape_once().await;
+            }
+            status.set_next_run(Utc::now() + chrono::Duration::seconds(SCRAPE_INTERVAL_SECS));
+            tokio::time::sleep(std::time::Duration::from_secs(SCRAPE_INTERVAL_SECS as u64)).await;
+        }
+    }
+
+    /// Scrapes every tracked product once and records the results.
+    pub async fn scrape_once(&self) {
         // Scope for Improvement: Do we need Arc here?
         let db = self.db.clone();
+        let status = ScraperStatus::get();
 
-        loop {
-            if let Ok(preferences) = notification_preferences::Entity::find().all(&*db).await {
-                let product_ids: Vec<i32> =
-                    preferences.iter().map(|pref| pref.product_id).collect();
-
-                match scrape_products(product_ids)
-                    .await
-                    .map_err(|e| e.to_string())
-                {
-                    Ok(prices) => {
-                        for (pref, price) in preferences.iter().zip(prices) {
-                            // let decimal_price = Decimal::new(price as i64, 2);
-                            update_prices(&db, pref.product_id, Decimal::from_str(&price).unwrap())
-                                .await;
-                        }
-                    }
-                    Err(e) => tracing::error!("Scraping error: {}", e),
+        let preferences = match notification_preferences::Entity::find().all(&*db).await {
+            Ok(preferences) => preferences,
+            Err(e) => {
+                tracing::error!("Could not load tracked products: {}", e);
+                return;
+            }
+        };
+        let watched = match channel_watchlists::Entity::find().all(&*db).await {
+            Ok(watched) => watched,
+            Err(e) => {
+                tracing::error!("Could not load watchlists: {}", e);
+                return;
+            }
+        };
+        // Products tracked by several people or channels are scraped once
+        let mut product_ids: Vec<i32> = preferences
+            .iter()
+            .map(|pref| pref.product_id)
+            .chain(watched.iter().map(|watch| watch.product_id))
+            .collect();
+        product_ids.sort_unstable();
+        product_ids.dedup();
+        status.retain_tracked(&product_ids);
+        self.scrape_products(product_ids).await;
+    }
+
+    /// One run over `product_ids`: records their prices, then announces deals or a sale.
+    pub async fn scrape_products(&self, product_ids: Vec<i32>) {
+        let db = self.db.clone();
+        let status = ScraperStatus::get();
+        let _run = status.lock_run().await;
+        let started = Instant::now();
+        let mut report = RunReport::new(Utc::now());
+        status.run_started(report.started_at);
+        let mut changes = Vec::new();
+        for product_id in product_ids {
+            if status.is_paused() {
+                report.interrupted = true;
+                break;
+            }
+            match self.scrape_recorded(product_id).await {
+                Ok(change) => {
+                    report.count(None);
+                    changes.push(change);
+                }
+                Err(failure) => {
+                    tracing::warn!("Skipping product {}: {}", product_id, failure);
+                    report.count(Some(&failure));
                 }
             }
-            tokio::time::sleep(std::time::Duration::from_secs(3600)).await;
         }
+        report.duration = started.elapsed();
+
+        // Deals and watchlist posts wait for the end of the run, when a store-wide sale can
+        // be told apart
+        let sale = match &self.sales {
+            Some(sales) => sales.detect(&db, &changes, report.products).await,
+            None => false,
+        };
+        if sale {
+            if let Some(watchlists) = &self.watchlists {
+                watchlists.announce_sale(&db, &changes).await;
+            }
+        } else {
+            for change in &changes {
+                self.announce_deal(change).await;
+                self.announce_watchlist(change).await;
+            }
+        }
+        tracing::info!(
+            "Scraped {} products in {}s, {} failed",
+            report.products,
+            report.duration.as_secs(),
+            report.failures()
+        );
+        status.run_finished(report);
+    }
+
+    /// Scrapes one tracked product and stores its new price, returning the updated row.
+    pub async fn scrape_product(&self, product_id: i32) -> Result<products::Model, ScrapeFailure> {
+        let change = self.scrape_recorded(product_id).await?;
+        self.announce_deal(&change).await;
+        self.announce_watchlist(&change).await;
+        Ok(change.after)
+    }
+
+    async fn scrape_recorded(&self, product_id: i32) -> Result<PriceChange, ScrapeFailure> {
+        let result = self.scrape_and_update(product_id).await;
+        ScraperStatus::get().record(product_id, result.as_ref().err());
+        result
+    }
+
+    async fn announce_deal(&self, change: &PriceChange) {
+        if let Some(deals) = &self.deals {
+            deals.announce(&self.db, change).await;
+        }
+    }
+
+    async fn announce_watchlist(&self, change: &PriceChange) {
+        if let Some(watchlists) = &self.watchlists {
+            watchlists.announce(&self.db, change).await;
+        }
+    }
+
+    async fn scrape_and_update(&self, product_id: i32) -> Result<PriceChange, ScrapeFailure> {
+        let page = self
+            .fetcher
+            .fetch(product_id)
+            .await
+            .map_err(ScrapeFailure::Fetch)?;
+        let snapshot = parse_product_page(&page);
+
+        // A captcha or error page says nothing about the price; recording it would store ₹0
+        if snapshot.blocked {
+            return Err(ScrapeFailure::Blocked);
+        }
+        if !snapshot.is_product_page() {
+            return Err(ScrapeFailure::NotAProductPage);
+        }
+
+        update_prices(&self.db, product_id, &snapshot)
+            .await
+            .ok_or(ScrapeFailure::Save)
     }
 }
 
-// Insert into Price History and Update product table
-async fn update_prices(db: &DatabaseConnection, product_id: i32, price: Decimal) {
+// Insert into Price History and Update product table; returns the product row before and
+// after the update. Only called with pages that show a product
+async fn update_prices(
+    db: &DatabaseConnection,
+    product_id: i32,
+    snapshot: &ProductSnapshot,
+) -> Option<PriceChange> {
     // Scope for Improvement : Make a transaction
+    let price = snapshot.price;
     let history = price_history::ActiveModel {
         product_id: Set(product_id),
         price: Set(price),
         recorded_at: Set(chrono::Utc::now().naive_utc()),
+        mrp: Set(snapshot.mrp),
+        discount_percent: Set(snapshot.discount_percent),
+        in_stock: Set(snapshot.in_stock),
         ..Default::default()
     };
 
     if let Err(e) = history.insert(db).await {
         eprintln!("Failed to insert price history: {}", e);
-        return;
+        return None;
     }
 
+    let mut change = None;
     if let Ok(Some(current_product)) = products::Entity::find_by_id(product_id).one(db).await {
         let mut product_update: products::ActiveModel = current_product.clone().into();
         product_update.current_price = Set(price);
         product_update.last_updated = Set(chrono::Utc::now().naive_utc());
+        product_update.mrp = Set(snapshot.mrp);
+        product_update.discount_percent = Set(snapshot.discount_percent);
+        product_update.in_stock = Set(snapshot.in_stock);
+        apply_metadata(&mut product_update, snapshot);
 
         if price > current_product.highest_price {
             product_update.highest_price = Set(price);
         }
-        if price < current_product.lowest_price {
+        // A failed scrape records zero, which must not become the all-time low
+        if !price.is_zero() && price < current_product.lowest_price {
             product_update.lowest_price = Set(price);
         }
 
-        if let Err(e) = product_update.update(db).await {
-            eprintln!("Failed to update product: {}", e);
+        match product_update.update(db).await {
+            Ok(updated) => {
+                change = Some(PriceChange {
+                    before: current_product,
+                    after: updated,
+                })
+            }
+            Err(e) => eprintln!("Failed to update product: {}", e),
+        }
+    }
+
+    if let Err(e) = update_sizes(db, product_id, snapshot).await {
+        eprintln!("Failed to update product sizes: {}", e);
+    }
+    change
+}
+
+// Refresh title, brand, image and link, keeping the stored value when a selector came back empty
+pub fn apply_metadata(product: &mut products::ActiveModel, snapshot: &ProductSnapshot) {
+    if let Some(name) = &snapshot.name {
+        product.name = Set(Some(name.clone()));
+    }
+    if let Some(brand) = &snapshot.brand {
+        product.brand = Set(Some(brand.clone()));
+    }
+    if let Some(image_url) = &snapshot.image_url {
+        product.image_url = Set(Some(image_url.clone()));
+    }
+    if let Some(canonical_url) = &snapshot.canonical_url {
+        product.canonical_url = Set(Some(canonical_url.clone()));
+    }
+}
+
+// Keep one row per size and remember when a sold-out size came back
+pub async fn update_sizes(
+    db: &impl ConnectionTrait,
+    product_id: i32,
+    snapshot: &ProductSnapshot,
+) -> Result<(), DbErr> {
+    let now = chrono::Utc::now().naive_utc();
+    let existing = product_sizes::Entity::find()
+        .filter(product_sizes::Column::ProductId.eq(product_id))
+        .all(db)
+        .await?;
+
+    for size in &snapshot.sizes {
+        match existing.iter().find(|row| row.label == size.label) {
+            Some(row) => {
+                let mut update: product_sizes::ActiveModel = row.clone().into();
+                if size.in_stock && !row.in_stock {
+                    update.restocked_at = Set(Some(now));
+                }
+                update.in_stock = Set(size.in_stock);
+                update.updated_at = Set(now);
+                update.update(db).await?;
+            }
+            None => {
+                product_sizes::ActiveModel {
+                    product_id: Set(product_id),
+                    label: Set(size.label.clone()),
+                    in_stock: Set(size.in_stock),
+                    restocked_at: Set(None),
+                    updated_at: Set(now),
+                    ..Default::default()
+                }
+                .insert(db)
+                .await?;
+            }
         }
     }
+    Ok(())
 }
@@ -0,0 +1,278 @@
+use ::entity::{deal_channels, notification_preferences, sale_events};
+use chrono::{Duration, Utc};
+use sea_orm::prelude::Decimal;
+use sea_orm::*;
+use serenity::all::{ChannelId, CreateMessage, Http};
+use shuttle_runtime::SecretStore;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use super::deals::PriceChange;
+use crate::cron::notifications::percent_change;
+use crate::utils::embeds::sale_embed;
+
+const DEFAULT_MIN_PRODUCTS: usize = 8;
+const DEFAULT_MIN_SHARE_PERCENT: usize = 20;
+const DEFAULT_MIN_DROP_PERCENT: i64 = 10;
+const DEFAULT_MAX_DAYS: i64 = 4;
+
+/// When a run counts as a store-wide sale. A product counts when it fell by at least
+/// `SALE_MIN_DROP_PERCENT`; a sale needs `SALE_MIN_PRODUCTS` such products making up at
+/// least `SALE_MIN_SHARE_PERCENT` of the run. A sale ends when prices rise the same way
+/// or after `SALE_MAX_DAYS`.
+pub struct SaleConfig {
+    min_products: usize,
+    min_share_percent: usize,
+    min_drop_percent: Decimal,
+    max_days: i64,
+}
+
+impl SaleConfig {
+    pub fn from_secrets(secrets: &SecretStore) -> Result<Self, Box<dyn std::error::Error>> {
+        let min_products = match secrets.get("SALE_MIN_PRODUCTS") {
+            Some(count) => count.parse()?,
+            None => DEFAULT_MIN_PRODUCTS,
+        };
+        let min_share_percent = match secrets.get("SALE_MIN_SHARE_PERCENT") {
+            Some(percent) => percent.parse()?,
+            None => DEFAULT_MIN_SHARE_PERCENT,
+        };
+        let min_drop_percent: i64 = match secrets.get("SALE_MIN_DROP_PERCENT") {
+            Some(percent) => percent.parse()?,
+            None => DEFAULT_MIN_DROP_PERCENT,
+        };
+        let max_days = match secrets.get("SALE_MAX_DAYS") {
+            Some(days) => days.parse()?,
+            None => DEFAULT_MAX_DAYS,
+        };
+        if min_products < 2 {
+            return Err("SALE_MIN_PRODUCTS must be at least 2".into());
+        }
+        if !(1..100).contains(&min_drop_percent) {
+            return Err("SALE_MIN_DROP_PERCENT must be between 1 and 99".into());
+        }
+        if max_days < 1 {
+            return Err("SALE_MAX_DAYS must be at least 1".into());
+        }
+        Ok(Self {
+            min_products,
+            min_share_percent,
+            min_drop_percent: Decimal::from(min_drop_percent),
+            max_days,
+        })
+    }
+
+    /// Whether `moved` of `scraped` products moving together is store-wide.
+    fn is_wave(&self, moved: usize, scraped: usize) -> bool {
+        moved >= self.min_products && moved * 100 >= scraped * self.min_share_percent
+    }
+}
+
+/// A product that fell in a run, with its drop in percent.
+pub struct SaleDrop<'a> {
+    pub change: &'a PriceChange,
+    pub percent: Decimal,
+}
+
+/// Drops of at least `min_percent`, biggest first. Zero prices are failed scrapes.
+pub fn ranked_drops(changes: &[PriceChange], min_percent: Decimal) -> Vec<SaleDrop<'_>> {
+    let mut drops: Vec<SaleDrop> = changes
+        .iter()
+        .filter(|change| {
+            !change.before.current_price.is_zero() && !change.after.current_price.is_zero()
+        })
+        .map(|change| SaleDrop {
+            change,
+            percent: -percent_change(change.before.current_price, change.after.current_price),
+        })
+        .filter(|drop| drop.percent >= min_percent)
+        .collect();
+    drops.sort_by_key(|drop| std::cmp::Reverse(drop.percent));
+    drops
+}
+
+fn rises(changes: &[PriceChange], min_percent: Decimal) -> usize {
+    changes
+        .iter()
+        .filter(|change| {
+            !change.before.current_price.is_zero()
+                && percent_change(change.before.current_price, change.after.current_price)
+                    >= min_percent
+        })
+        .count()
+}
+
+/// Notices runs where many tracked products fall at once and posts one "sale started"
+/// summary instead of a deal post per product. While the sale is open, the notification
+/// loop folds price alerts into one summary per recipient as well.
+pub struct SaleDetector {
+    config: SaleConfig,
+    http: Arc<Http>,
+}
+
+impl SaleDetector {
+    pub fn new(config: SaleConfig, http: Arc<Http>) -> Self {
+        Self { config, http }
+    }
+
+    /// Looks at the price changes of one run of `scraped` products. Returns whether the
+    /// run was a store-wide drop, whose drops the sale summary then covers.
+    pub async fn detect(
+        &self,
+        db: &DatabaseConnection,
+        changes: &[PriceChange],
+        scraped: usize,
+    ) -> bool {
+        let open = match self.open_sale(db, changes, scraped).await {
+            Ok(open) => open,
+            Err(e) => {
+                tracing::error!("Failed to load the open sale: {}", e);
+                return false;
+            }
+        };
+
+        let drops = ranked_drops(changes, self.config.min_drop_percent);
+        if !self.config.is_wave(drops.len(), scraped) {
+            return false;
+        }
+        if let Some(sale) = open {
+            tracing::info!(
+                "{} products dropped during sale {}",
+                drops.len(),
+                sale.sale_id
+            );
+            return true;
+        }
+
+        let average =
+            drops.iter().map(|drop| drop.percent).sum::<Decimal>() / Decimal::from(drops.len());
+        let sale = sale_events::ActiveModel {
+            started_at: Set(Utc::now().naive_utc()),
+            ended_at: Set(None),
+            products_scraped: Set(scraped as i32),
+            products_dropped: Set(drops.len() as i32),
+            average_drop_percent: Set(average.round_dp(2)),
+            ..Default::default()
+        }
+        .insert(db)
+        .await;
+        match sale {
+            Ok(sale) => {
+                tracing::info!(
+                    "Sale {} started: {} of {} products dropped",
+                    sale.sale_id,
+                    drops.len(),
+                    scraped
+                );
+                self.announce(db, &drops, scraped).await;
+                true
+            }
+            Err(e) => {
+                tracing::error!("Failed to save sale event: {}", e);
+                false
+            }
+        }
+    }
+
+    // The running sale, after closing it when prices went back up or it ran too long
+    async fn open_sale(
+        &self,
+        db: &DatabaseConnection,
+        changes: &[PriceChange],
+        scraped: usize,
+    ) -> Result<Option<sale_events::Model>, DbErr> {
+        let Some(sale) = sale_events::Entity::find()
+            .filter(sale_events::Column::EndedAt.is_null())
+            .order_by_desc(sale_events::Column::StartedAt)
+            .one(db)
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        let now = Utc::now().naive_utc();
+        let expired = now - sale.started_at > Duration::days(self.config.max_days);
+        let prices_back = self
+            .config
+            .is_wave(rises(changes, self.config.min_drop_percent), scraped);
+        if !expired && !prices_back {
+            return Ok(Some(sale));
+        }
+
+        tracing::info!("Sale {} ended", sale.sale_id);
+        let mut ended: sale_events::ActiveModel = sale.into();
+        ended.ended_at = Set(Some(now));
+        ended.update(db).await?;
+        Ok(None)
+    }
+
+    async fn announce(&self, db: &DatabaseConnection, drops: &[SaleDrop<'_>], scraped: usize) {
+        let summary = format!(
+            "{} of {} tracked products just dropped by {}% or more.",
+            drops.len(),
+            scraped,
+            self.config.min_drop_percent
+        );
+
+        let product_ids: Vec<i32> = drops
+            .iter()
+            .map(|drop| drop.change.after.product_id)
+            .collect();
+        let tracked = match tracked_by_guild(db, product_ids).await {
+            Ok(tracked) => tracked,
+            Err(e) => {
+                tracing::error!("Failed to load tracked products of guilds: {}", e);
+                return;
+            }
+        };
+
+        match deal_channels::Entity::find().all(db).await {
+            Ok(channels) => {
+                for channel in channels {
+                    // Each guild only sees the drops of products its members track
+                    let Some(tracked_here) = tracked.get(&channel.guild_id) else {
+                        continue;
+                    };
+                    let ranked: Vec<&SaleDrop> = drops
+                        .iter()
+                        .filter(|drop| tracked_here.contains(&drop.change.after.product_id))
+                        .collect();
+                    let message = CreateMessage::new().embed(sale_embed(
+                        "🎉 A sale started on Myntra",
+                        &summary,
+                        &ranked,
+                    ));
+                    if let Err(e) = ChannelId::new(channel.channel_id as u64)
+                        .send_message(&self.http, message)
+                        .await
+                    {
+                        tracing::error!("Failed to post sale in guild {}: {}", channel.guild_id, e);
+                    }
+                }
+            }
+            Err(e) => tracing::error!("Failed to load deal channels: {}", e),
+        }
+    }
+}
+
+/// Which of `product_ids` each guild's members track.
+async fn tracked_by_guild(
+    db: &DatabaseConnection,
+    product_ids: Vec<i32>,
+) -> Result<HashMap<i64, HashSet<i32>>, DbErr> {
+    let trackers = notification_preferences::Entity::find()
+        .filter(notification_preferences::Column::ProductId.is_in(product_ids))
+        .filter(notification_preferences::Column::GuildId.is_not_null())
+        .all(db)
+        .await?;
+    let mut tracked: HashMap<i64, HashSet<i32>> = HashMap::new();
+    for tracker in trackers {
+        if let Some(guild_id) = tracker.guild_id {
+            tracked
+                .entry(guild_id)
+                .or_default()
+                .insert(tracker.product_id);
+        }
+    }
+    Ok(tracked)
+}
@@ -0,0 +1,195 @@
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+
+static STATUS: Lazy<ScraperStatus> = Lazy::new(ScraperStatus::default);
+
+/// What the hourly scrape loop is doing and how its runs went, for `/scraper status`.
+/// Kept in memory; a restart starts with a clean slate and the loop running.
+#[derive(Default)]
+pub struct ScraperStatus {
+    paused: AtomicBool,
+    state: Mutex<State>,
+    run: tokio::sync::Mutex<()>,
+}
+
+#[derive(Default)]
+struct State {
+    running_since: Option<DateTime<Utc>>,
+    next_run: Option<DateTime<Utc>>,
+    last_run: Option<RunReport>,
+    broken: HashMap<i32, BrokenProduct>,
+}
+
+/// Why one product could not be scraped.
+#[derive(Clone, Debug)]
+pub enum ScrapeFailure {
+    /// The page did not load at all.
+    Fetch(String),
+    Blocked,
+    /// The page loaded but shows no product, e.g. it was taken down.
+    NotAProductPage,
+    /// The product was scraped but the new price could not be stored.
+    Save,
+}
+
+impl fmt::Display for ScrapeFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScrapeFailure::Fetch(e) => write!(f, "page did not load: {}", e),
+            ScrapeFailure::Blocked => write!(f, "blocked by bot protection"),
+            ScrapeFailure::NotAProductPage => write!(f, "not a product page"),
+            ScrapeFailure::Save => write!(f, "could not save the price"),
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct RunReport {
+    pub started_at: DateTime<Utc>,
+    pub duration: Duration,
+    pub products: usize,
+    pub updated: usize,
+    pub fetch_errors: usize,
+    pub blocked: usize,
+    pub not_product_pages: usize,
+    pub save_errors: usize,
+    /// Pausing stops a run between two products.
+    pub interrupted: bool,
+}
+
+impl RunReport {
+    pub fn new(started_at: DateTime<Utc>) -> Self {
+        Self {
+            started_at,
+            ..Default::default()
+        }
+    }
+
+    /// Counts one scraped product; `None` when it was updated.
+    pub fn count(&mut self, failure: Option<&ScrapeFailure>) {
+        self.products += 1;
+        match failure {
+            None => self.updated += 1,
+            Some(ScrapeFailure::Fetch(_)) => self.fetch_errors += 1,
+            Some(ScrapeFailure::Blocked) => self.blocked += 1,
+            Some(ScrapeFailure::NotAProductPage) => self.not_product_pages += 1,
+            Some(ScrapeFailure::Save) => self.save_errors += 1,
+        }
+    }
+
+    pub fn failures(&self) -> usize {
+        self.products - self.updated
+    }
+}
+
+/// A product whose latest scrapes all failed.
+#[derive(Clone)]
+pub struct BrokenProduct {
+    pub product_id: i32,
+    pub failure: ScrapeFailure,
+    pub failures_in_row: u32,
+    pub since: DateTime<Utc>,
+}
+
+/// Copy of the status at one moment.
+pub struct StatusSnapshot {
+    pub paused: bool,
+    pub running_since: Option<DateTime<Utc>>,
+    pub next_run: Option<DateTime<Utc>>,
+    pub last_run: Option<RunReport>,
+    /// Most failures in a row first.
+    pub broken: Vec<BrokenProduct>,
+}
+
+impl ScraperStatus {
+    pub fn get() -> &'static ScraperStatus {
+        &STATUS
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    /// Returns whether the loop was paused before.
+    pub fn set_paused(&self, paused: bool) -> bool {
+        self.paused.swap(paused, Ordering::Relaxed)
+    }
+
+    /// Held for a whole run, sale detection included. Price alerts take it too, so they
+    /// are never evaluated against a half finished run.
+    pub async fn lock_run(&self) -> tokio::sync::MutexGuard<'_, ()> {
+        self.run.lock().await
+    }
+
+    pub fn run_started(&self, started_at: DateTime<Utc>) {
+        self.state().running_since = Some(started_at);
+    }
+
+    pub fn run_finished(&self, report: RunReport) {
+        let mut state = self.state();
+        state.running_since = None;
+        state.last_run = Some(report);
+    }
+
+    pub fn set_next_run(&self, next_run: DateTime<Utc>) {
+        self.state().next_run = Some(next_run);
+    }
+
+    /// Remembers the outcome of scraping one product, from a run or a forced scrape.
+    pub fn record(&self, product_id: i32, failure: Option<&ScrapeFailure>) {
+        let mut state = self.state();
+        match failure {
+            None => {
+                state.broken.remove(&product_id);
+            }
+            Some(failure) => {
+                let broken = state
+                    .broken
+                    .entry(product_id)
+                    .or_insert_with(|| BrokenProduct {
+                        product_id,
+                        failure: failure.clone(),
+                        failures_in_row: 0,
+                        since: Utc::now(),
+                    });
+                broken.failure = failure.clone();
+                broken.failures_in_row += 1;
+            }
+        }
+    }
+
+    /// Drops products that are no longer tracked from the broken list.
+    pub fn retain_tracked(&self, product_ids: &[i32]) {
+        self.state()
+            .broken
+            .retain(|product_id, _| product_ids.binary_search(product_id).is_ok());
+    }
+
+    pub fn snapshot(&self) -> StatusSnapshot {
+        let state = self.state();
+        let mut broken: Vec<BrokenProduct> = state.broken.values().cloned().collect();
+        broken.sort_by(|a, b| {
+            b.failures_in_row
+                .cmp(&a.failures_in_row)
+                .then(a.product_id.cmp(&b.product_id))
+        });
+        StatusSnapshot {
+            paused: self.is_paused(),
+            running_since: state.running_since,
+            next_run: state.next_run,
+            last_run: state.last_run.clone(),
+            broken,
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
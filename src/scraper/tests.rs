@@ -0,0 +1,309 @@
+use ::entity::{notification_preferences, price_history, product_sizes, products};
+use axum::extract::Path;
+use axum::http::StatusCode;
+use axum::routing::get;
+use axum::Router;
+use migration::{Migrator, MigratorTrait};
+use sea_orm::prelude::Decimal;
+use sea_orm::*;
+
+use super::deals::PriceChange;
+use super::fetch::HttpFetcher;
+use super::myntra::parse_product_page;
+use super::price_scraper::PriceScraper;
+use super::sales::ranked_drops;
+use super::status::ScraperStatus;
+
+const IN_STOCK: &str = include_str!("../../tests/fixtures/myntra/in_stock.html");
+const OUT_OF_STOCK: &str = include_str!("../../tests/fixtures/myntra/out_of_stock.html");
+const DISCOUNTED: &str = include_str!("../../tests/fixtures/myntra/discounted.html");
+const BLOCKED: &str = include_str!("../../tests/fixtures/myntra/blocked.html");
+const CAPTCHA: &str = include_str!("../../tests/fixtures/myntra/captcha.html");
+const REMOVED: &str = include_str!("../../tests/fixtures/myntra/removed.html");
+
+#[test]
+fn parses_in_stock_page() {
+    let snapshot = parse_product_page(IN_STOCK);
+
+    assert_eq!(snapshot.price, Decimal::from(1299));
+    assert_eq!(snapshot.mrp, Some(Decimal::from(2599)));
+    assert_eq!(snapshot.discount_percent, Some(50));
+    assert!(snapshot.in_stock);
+    assert_eq!(snapshot.brand.as_deref(), Some("Roadster"));
+    assert_eq!(
+        snapshot.name.as_deref(),
+        Some("Men Navy Blue Solid Round Neck T-shirt")
+    );
+    assert!(snapshot
+        .image_url
+        .as_deref()
+        .is_some_and(|url| url.contains("1234567")));
+    assert!(snapshot
+        .canonical_url
+        .as_deref()
+        .is_some_and(|url| url.ends_with("/1234567/buy")));
+
+    let sizes: Vec<(&str, bool)> = snapshot
+        .sizes
+        .iter()
+        .map(|size| (size.label.as_str(), size.in_stock))
+        .collect();
+    assert_eq!(sizes, [("S", false), ("M", true), ("L", true)]);
+    assert!(!snapshot.blocked);
+    assert!(snapshot.is_product_page());
+}
+
+#[test]
+fn parses_out_of_stock_page() {
+    let snapshot = parse_product_page(OUT_OF_STOCK);
+
+    assert_eq!(snapshot.price, Decimal::from(899));
+    assert!(!snapshot.in_stock);
+    assert!(snapshot.sizes.is_empty());
+    assert_eq!(snapshot.mrp, None);
+    assert_eq!(snapshot.discount_percent, None);
+    assert!(snapshot.is_product_page());
+}
+
+#[test]
+fn derives_flat_discount_from_mrp() {
+    let snapshot = parse_product_page(DISCOUNTED);
+
+    assert_eq!(snapshot.price, Decimal::from(1499));
+    assert_eq!(snapshot.mrp, Some(Decimal::from(1999)));
+    assert_eq!(snapshot.discount_percent, Some(25));
+    assert!(snapshot.in_stock);
+    // Falls back to og:url without a canonical link
+    assert!(snapshot
+        .canonical_url
+        .as_deref()
+        .is_some_and(|url| url.ends_with("/3456789/buy")));
+}
+
+#[test]
+fn recognises_blocked_pages() {
+    for page in [BLOCKED, CAPTCHA] {
+        let snapshot = parse_product_page(page);
+        assert!(snapshot.blocked);
+        assert!(snapshot.price.is_zero());
+        assert!(!snapshot.is_product_page());
+    }
+}
+
+#[test]
+fn recognises_removed_products() {
+    let snapshot = parse_product_page(REMOVED);
+
+    assert!(!snapshot.blocked);
+    assert!(snapshot.price.is_zero());
+    assert_eq!(snapshot.name, None);
+    assert!(!snapshot.is_product_page());
+}
+
+fn price_change(product_id: i32, before: i64, after: i64) -> PriceChange {
+    let product = |price: i64| products::Model {
+        product_id,
+        current_price: Decimal::from(price),
+        highest_price: Decimal::from(before.max(after)),
+        lowest_price: Decimal::from(before.min(after)),
+        last_updated: chrono::Utc::now().naive_utc(),
+        mrp: None,
+        discount_percent: None,
+        in_stock: true,
+        name: None,
+        brand: None,
+        image_url: None,
+        canonical_url: None,
+    };
+    PriceChange {
+        before: product(before),
+        after: product(after),
+    }
+}
+
+#[test]
+fn ranks_sale_drops_biggest_first() {
+    let changes = vec![
+        price_change(1, 1000, 900),
+        price_change(2, 1000, 500),
+        price_change(3, 1000, 1100),
+        price_change(4, 1000, 950),
+        price_change(5, 1000, 0),
+        price_change(6, 2000, 1500),
+    ];
+
+    let drops = ranked_drops(&changes, Decimal::from(10));
+
+    let ranked: Vec<(i32, Decimal)> = drops
+        .iter()
+        .map(|drop| (drop.change.after.product_id, drop.percent))
+        .collect();
+    assert_eq!(
+        ranked,
+        vec![
+            (2, Decimal::from(50)),
+            (6, Decimal::from(25)),
+            (1, Decimal::from(10)),
+        ]
+    );
+}
+
+// Ids well above real Myntra ids so the test never touches other rows
+const IN_STOCK_ID: i32 = 990_000_001;
+const BLOCKED_ID: i32 = 990_000_002;
+
+async fn fixture_page(Path(product_id): Path<i32>) -> (StatusCode, &'static str) {
+    match product_id {
+        IN_STOCK_ID => (StatusCode::OK, IN_STOCK),
+        BLOCKED_ID => (StatusCode::OK, BLOCKED),
+        _ => (StatusCode::NOT_FOUND, REMOVED),
+    }
+}
+
+/// Serves the fixtures on a random local port and returns its base URL.
+async fn fixture_server() -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("bind fixture server");
+    let addr = listener.local_addr().expect("fixture server address");
+    let app = Router::new().route("/:product_id", get(fixture_page));
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    format!("http://{}", addr)
+}
+
+async fn insert_tracked_product(db: &DatabaseConnection, product_id: i32) {
+    let now = chrono::Utc::now().naive_utc();
+    products::ActiveModel {
+        product_id: Set(product_id),
+        current_price: Set(Decimal::from(1500)),
+        highest_price: Set(Decimal::from(1500)),
+        lowest_price: Set(Decimal::from(1500)),
+        last_updated: Set(now),
+        mrp: Set(None),
+        discount_percent: Set(None),
+        in_stock: Set(true),
+        name: Set(None),
+        brand: Set(None),
+        image_url: Set(None),
+        canonical_url: Set(None),
+    }
+    .insert(db)
+    .await
+    .expect("insert product");
+
+    notification_preferences::ActiveModel {
+        product_id: Set(product_id),
+        email: Set(Some("fixture@example.com".to_string())),
+        time_interval_hours: Set(24),
+        price_threshold: Set(Decimal::ZERO),
+        notify_on_lowest: Set(false),
+        notify_on_highest: Set(false),
+        last_notified: Set(now),
+        created_at: Set(now),
+        updated_at: Set(now),
+        watch_size: Set(None),
+        discount_threshold: Set(None),
+        drop_percent: Set(None),
+        notify_on_restock: Set(false),
+        periodic_digest: Set(false),
+        discord_user_id: Set(None),
+        // Inactive so a notification run against the same database never emails it
+        active: Set(false),
+        paused: Set(false),
+        snoozed_until: Set(None),
+        notify_email: Set(true),
+        notify_dm: Set(false),
+        discord_channel_id: Set(None),
+        webhook_url: Set(None),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .expect("insert preference");
+}
+
+/// Runs `PriceScraper` against the fixture server and a real Postgres. Needs
+/// `TEST_DATABASE_URL` pointing at a database the test may migrate.
+#[tokio::test]
+#[ignore = "needs TEST_DATABASE_URL; run with --include-ignored"]
+async fn scraper_records_fixture_prices() {
+    let url = std::env::var("TEST_DATABASE_URL").expect("TEST_DATABASE_URL is set");
+    let db = Database::connect(url)
+        .await
+        .expect("connect to test database");
+    Migrator::up(&db, None).await.expect("run migrations");
+
+    // Leftovers of an aborted run; deleting products cascades to everything else
+    products::Entity::delete_many()
+        .filter(products::Column::ProductId.is_in([IN_STOCK_ID, BLOCKED_ID]))
+        .exec(&db)
+        .await
+        .expect("clean up products");
+    insert_tracked_product(&db, IN_STOCK_ID).await;
+    insert_tracked_product(&db, BLOCKED_ID).await;
+
+    let scraper = PriceScraper::with_fetcher(
+        db.clone(),
+        Box::new(HttpFetcher::new(fixture_server().await)),
+    );
+    // Only the fixtures; other products in a shared database are left alone
+    scraper.scrape_products(vec![IN_STOCK_ID, BLOCKED_ID]).await;
+
+    let status = ScraperStatus::get().snapshot();
+    assert!(status
+        .last_run
+        .is_some_and(|run| run.updated >= 1 && run.blocked >= 1));
+    let broken: Vec<i32> = status
+        .broken
+        .iter()
+        .map(|broken| broken.product_id)
+        .collect();
+    assert!(broken.contains(&BLOCKED_ID));
+    assert!(!broken.contains(&IN_STOCK_ID));
+
+    let product = products::Entity::find_by_id(IN_STOCK_ID)
+        .one(&db)
+        .await
+        .expect("load product")
+        .expect("product exists");
+    assert_eq!(product.current_price, Decimal::from(1299));
+    assert_eq!(product.lowest_price, Decimal::from(1299));
+    assert_eq!(product.highest_price, Decimal::from(1500));
+    assert_eq!(product.discount_percent, Some(50));
+    assert_eq!(product.brand.as_deref(), Some("Roadster"));
+
+    let history = price_history::Entity::find()
+        .filter(price_history::Column::ProductId.eq(IN_STOCK_ID))
+        .all(&db)
+        .await
+        .expect("load history");
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].price, Decimal::from(1299));
+
+    let sizes = product_sizes::Entity::find()
+        .filter(product_sizes::Column::ProductId.eq(IN_STOCK_ID))
+        .count(&db)
+        .await
+        .expect("load sizes");
+    assert_eq!(sizes, 3);
+
+    // The blocked page must not be stored as a ₹0 price
+    let blocked = products::Entity::find_by_id(BLOCKED_ID)
+        .one(&db)
+        .await
+        .expect("load blocked product")
+        .expect("blocked product exists");
+    assert_eq!(blocked.current_price, Decimal::from(1500));
+    let blocked_history = price_history::Entity::find()
+        .filter(price_history::Column::ProductId.eq(BLOCKED_ID))
+        .count(&db)
+        .await
+        .expect("load blocked history");
+    assert_eq!(blocked_history, 0);
+
+    products::Entity::delete_many()
+        .filter(products::Column::ProductId.is_in([IN_STOCK_ID, BLOCKED_ID]))
+        .exec(&db)
+        .await
+        .expect("clean up products");
+}
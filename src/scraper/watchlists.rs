@@ -0,0 +1,171 @@
+use ::entity::channel_watchlists;
+use sea_orm::prelude::Decimal;
+use sea_orm::*;
+use serenity::all::{ChannelId, CreateActionRow, CreateButton, CreateMessage, Http};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use super::deals::PriceChange;
+use crate::cron::notifications::percent_change;
+use crate::utils::embeds::{summary_embed, watchlist_embed};
+use crate::utils::util::{product_display_name, product_url};
+
+// Smaller moves, like a rupee of rounding, are not worth a post in the channel
+const MIN_CHANGE_PERCENT: i64 = 3;
+
+/// Posts price events of watched products into every channel whose `/watchlist` has them.
+pub struct WatchlistAnnouncer {
+    http: Arc<Http>,
+}
+
+impl WatchlistAnnouncer {
+    pub fn new(http: Arc<Http>) -> Self {
+        Self { http }
+    }
+
+    pub async fn announce(&self, db: &DatabaseConnection, change: &PriceChange) {
+        let Some(headline) = headline(change) else {
+            return;
+        };
+        let after = &change.after;
+
+        let channels = match channel_watchlists::Entity::find()
+            .filter(channel_watchlists::Column::ProductId.eq(after.product_id))
+            .all(db)
+            .await
+        {
+            Ok(channels) => channels,
+            Err(e) => {
+                tracing::error!("Failed to load watchlists of {}: {}", after.product_id, e);
+                return;
+            }
+        };
+
+        for watch in channels {
+            let message = CreateMessage::new()
+                .embed(watchlist_embed(
+                    after,
+                    &headline,
+                    change.before.current_price,
+                ))
+                .components(vec![CreateActionRow::Buttons(vec![
+                    CreateButton::new_link(product_url(after)).label("View on Myntra"),
+                ])]);
+
+            if let Err(e) = ChannelId::new(watch.channel_id as u64)
+                .send_message(&self.http, message)
+                .await
+            {
+                tracing::error!(
+                    "Failed to post watchlist update for product {} in channel {}: {}",
+                    after.product_id,
+                    watch.channel_id,
+                    e
+                );
+            }
+        }
+    }
+
+    /// Posts the changes of a sale run as one summary per channel instead of a post per
+    /// product, biggest drops first.
+    pub async fn announce_sale(&self, db: &DatabaseConnection, changes: &[PriceChange]) {
+        let mut headlines: Vec<(&PriceChange, String)> = changes
+            .iter()
+            .filter_map(|change| Some((change, headline(change)?)))
+            .collect();
+        if headlines.is_empty() {
+            return;
+        }
+        // Restocks and sellouts may come without a price to compare; they go after the drops
+        headlines.sort_by_key(|(change, _)| {
+            let (before, after) = (change.before.current_price, change.after.current_price);
+            if before.is_zero() || after.is_zero() {
+                Decimal::ZERO
+            } else {
+                percent_change(before, after)
+            }
+        });
+
+        let product_ids: Vec<i32> = headlines
+            .iter()
+            .map(|(change, _)| change.after.product_id)
+            .collect();
+        let watches = match channel_watchlists::Entity::find()
+            .filter(channel_watchlists::Column::ProductId.is_in(product_ids))
+            .all(db)
+            .await
+        {
+            Ok(watches) => watches,
+            Err(e) => {
+                tracing::error!("Failed to load watchlists for the sale: {}", e);
+                return;
+            }
+        };
+
+        let mut lines: BTreeMap<i64, Vec<String>> = BTreeMap::new();
+        for (change, headline) in &headlines {
+            let after = &change.after;
+            for watch in watches
+                .iter()
+                .filter(|watch| watch.product_id == after.product_id)
+            {
+                lines.entry(watch.channel_id).or_default().push(format!(
+                    "[{}]({}) **₹{}**: {}",
+                    product_display_name(after),
+                    product_url(after),
+                    after.current_price,
+                    headline
+                ));
+            }
+        }
+
+        for (channel_id, lines) in lines {
+            let message = CreateMessage::new().embed(summary_embed(
+                "🎉 Sale on Myntra",
+                "Products on this watchlist just changed:",
+                lines,
+            ));
+            if let Err(e) = ChannelId::new(channel_id as u64)
+                .send_message(&self.http, message)
+                .await
+            {
+                tracing::error!(
+                    "Failed to post the sale summary in channel {}: {}",
+                    channel_id,
+                    e
+                );
+            }
+        }
+    }
+}
+
+// What changed worth telling the channel, or `None` for a scrape that changed nothing
+// or moved the price by less than `MIN_CHANGE_PERCENT`
+fn headline(change: &PriceChange) -> Option<String> {
+    let (before, after) = (&change.before, &change.after);
+    if !before.in_stock && after.in_stock {
+        return Some("📦 Back in stock".to_string());
+    }
+    if before.in_stock && !after.in_stock {
+        return Some("🚫 Sold out".to_string());
+    }
+    if before.current_price.is_zero()
+        || after.current_price.is_zero()
+        || after.current_price == before.current_price
+    {
+        return None;
+    }
+
+    let percent = percent_change(before.current_price, after.current_price).abs();
+    if percent < Decimal::from(MIN_CHANGE_PERCENT) {
+        return None;
+    }
+    let percent = percent.round_dp(0);
+    Some(if after.current_price < before.lowest_price {
+        format!("📉 New all-time low, down {}%", percent)
+    } else if after.current_price < before.current_price {
+        format!("⬇️ Price dropped {}%", percent)
+    } else {
+        format!("⬆️ Price went up {}%", percent)
+    })
+}
@@ -0,0 +1,287 @@
+use chrono::NaiveDate;
+use sea_orm::prelude::{DateTime, Decimal};
+use std::collections::BTreeMap;
+
+// Fewer days than this say nothing about trends
+const MIN_DAYS: usize = 7;
+// A day counts as a sale when its price is at least this far below the median
+const SALE_DISCOUNT_PERCENT: i64 = 10;
+
+/// Trend figures for one product, computed from its daily closing prices.
+pub struct PriceAnalytics {
+    pub average_7d: Option<Decimal>,
+    pub average_30d: Option<Decimal>,
+    /// Standard deviation of the daily closes as a percentage of their mean.
+    pub volatility_percent: Decimal,
+    pub last_sale: Option<NaiveDate>,
+    /// Average number of days between the starts of two sales; needs at least two sales.
+    pub sale_gap_days: Option<i64>,
+    /// 0 to 100: the share of days the product cost more than it does now.
+    pub deal_score: u8,
+}
+
+impl PriceAnalytics {
+    /// `history` may be in any order and may mix hourly and daily points; zero prices are
+    /// failed scrapes and are skipped. Returns `None` with less than a week of history.
+    pub fn compute(history: &[(DateTime, Decimal)], current_price: Decimal) -> Option<Self> {
+        let mut sorted: Vec<_> = history
+            .iter()
+            .filter(|(_, price)| !price.is_zero())
+            .collect();
+        sorted.sort_by_key(|(recorded_at, _)| *recorded_at);
+
+        // The last price of each day is its close
+        let closes: BTreeMap<NaiveDate, Decimal> = sorted
+            .into_iter()
+            .map(|(recorded_at, price)| (recorded_at.date(), *price))
+            .collect();
+        if closes.len() < MIN_DAYS || current_price.is_zero() {
+            return None;
+        }
+
+        let prices: Vec<Decimal> = closes.values().copied().collect();
+        let latest_day = *closes.keys().next_back()?;
+        let (last_sale, sale_gap_days) = sale_cycle(&closes, median(&prices)?);
+
+        Some(Self {
+            average_7d: moving_average(&closes, latest_day, 7),
+            average_30d: moving_average(&closes, latest_day, 30),
+            volatility_percent: volatility_percent(&prices),
+            last_sale,
+            sale_gap_days,
+            deal_score: deal_score(&prices, current_price),
+        })
+    }
+
+    pub fn verdict(&self) -> &'static str {
+        match self.deal_score {
+            80.. => "Great time to buy",
+            60..=79 => "Good price",
+            40..=59 => "Average price",
+            _ => "Better to wait",
+        }
+    }
+
+    /// When the next sale is due if sales keep their usual rhythm.
+    pub fn next_sale(&self) -> Option<NaiveDate> {
+        Some(self.last_sale? + chrono::Duration::days(self.sale_gap_days?))
+    }
+
+    /// Label and value pairs, shared by the Discord embed and the email.
+    pub fn summary(&self) -> Vec<(&'static str, String)> {
+        let mut lines = vec![(
+            "Deal score",
+            format!("{}/100 · {}", self.deal_score, self.verdict()),
+        )];
+        if let Some(average) = self.average_7d {
+            lines.push(("7-day average", format!("₹{}", average.round_dp(0))));
+        }
+        if let Some(average) = self.average_30d {
+            lines.push(("30-day average", format!("₹{}", average.round_dp(0))));
+        }
+        lines.push((
+            "Volatility",
+            format!("{}%", self.volatility_percent.round_dp(1)),
+        ));
+        match (self.last_sale, self.sale_gap_days, self.next_sale()) {
+            (Some(last), Some(gap), Some(next)) => {
+                let next = if next > chrono::Utc::now().date_naive() {
+                    format!("next around {}", next.format("%d %b"))
+                } else {
+                    "the next one is due".to_string()
+                };
+                lines.push((
+                    "Sales",
+                    format!(
+                        "About every {} days, last on {}, {}",
+                        gap,
+                        last.format("%d %b"),
+                        next
+                    ),
+                ))
+            }
+            (Some(last), _, _) => {
+                lines.push(("Sales", format!("Last on {}", last.format("%d %b"))))
+            }
+            _ => {}
+        }
+        lines
+    }
+}
+
+fn moving_average(
+    closes: &BTreeMap<NaiveDate, Decimal>,
+    latest_day: NaiveDate,
+    days: i64,
+) -> Option<Decimal> {
+    let start = latest_day - chrono::Duration::days(days - 1);
+    // Only when the history reaches back over the whole window
+    if *closes.keys().next()? > start {
+        return None;
+    }
+    let window: Vec<Decimal> = closes.range(start..).map(|(_, price)| *price).collect();
+    Some(window.iter().sum::<Decimal>() / Decimal::from(window.len()))
+}
+
+fn volatility_percent(prices: &[Decimal]) -> Decimal {
+    let values: Vec<f64> = prices
+        .iter()
+        .filter_map(|price| f64::try_from(*price).ok())
+        .collect();
+    if values.is_empty() {
+        return Decimal::ZERO;
+    }
+    let mean = values.iter().sum::<f64>() / values.len() as f64;
+    if mean == 0.0 {
+        return Decimal::ZERO;
+    }
+    let variance = values
+        .iter()
+        .map(|value| (value - mean).powi(2))
+        .sum::<f64>()
+        / values.len() as f64;
+    Decimal::try_from(variance.sqrt() / mean * 100.0).unwrap_or_default()
+}
+
+fn median(prices: &[Decimal]) -> Option<Decimal> {
+    let mut sorted = prices.to_vec();
+    sorted.sort();
+    let middle = sorted.len() / 2;
+    match sorted.len() {
+        0 => None,
+        len if len.is_multiple_of(2) => {
+            Some((sorted[middle - 1] + sorted[middle]) / Decimal::from(2))
+        }
+        _ => Some(sorted[middle]),
+    }
+}
+
+/// Start of the latest sale and the average gap between sale starts. A sale is a run of
+/// days priced well below the median.
+fn sale_cycle(
+    closes: &BTreeMap<NaiveDate, Decimal>,
+    median: Decimal,
+) -> (Option<NaiveDate>, Option<i64>) {
+    let sale_price = median * Decimal::from(100 - SALE_DISCOUNT_PERCENT) / Decimal::from(100);
+
+    let mut starts = Vec::new();
+    let mut on_sale = false;
+    for (day, price) in closes {
+        let is_sale = *price <= sale_price;
+        if is_sale && !on_sale {
+            starts.push(*day);
+        }
+        on_sale = is_sale;
+    }
+
+    let gap = match (starts.first(), starts.last()) {
+        (Some(first), Some(last)) if starts.len() > 1 => {
+            Some((*last - *first).num_days() / (starts.len() as i64 - 1))
+        }
+        _ => None,
+    };
+    (starts.last().copied(), gap)
+}
+
+fn deal_score(prices: &[Decimal], current_price: Decimal) -> u8 {
+    if prices.is_empty() {
+        return 50;
+    }
+    // Days at the same price count half, so a flat history scores 50
+    let points: usize = prices
+        .iter()
+        .map(|price| match price.cmp(&current_price) {
+            std::cmp::Ordering::Greater => 2,
+            std::cmp::Ordering::Equal => 1,
+            std::cmp::Ordering::Less => 0,
+        })
+        .sum();
+    (points * 50 / prices.len()) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prices(values: &[i64]) -> Vec<Decimal> {
+        values.iter().copied().map(Decimal::from).collect()
+    }
+
+    fn closes(values: &[i64]) -> BTreeMap<NaiveDate, Decimal> {
+        let first = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+        values
+            .iter()
+            .enumerate()
+            .map(|(day, price)| {
+                (
+                    first + chrono::Duration::days(day as i64),
+                    Decimal::from(*price),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn median_of_odd_even_and_empty_input() {
+        assert_eq!(median(&prices(&[300, 100, 200])), Some(Decimal::from(200)));
+        assert_eq!(
+            median(&prices(&[400, 100, 200, 300])),
+            Some(Decimal::from(250))
+        );
+        assert_eq!(median(&prices(&[150])), Some(Decimal::from(150)));
+        assert_eq!(median(&[]), None);
+    }
+
+    #[test]
+    fn volatility_of_flat_varying_and_empty_input() {
+        assert_eq!(volatility_percent(&prices(&[500, 500, 500])), Decimal::ZERO);
+        assert_eq!(volatility_percent(&prices(&[500])), Decimal::ZERO);
+        assert_eq!(volatility_percent(&[]), Decimal::ZERO);
+        // Mean 100, standard deviation 10
+        let volatility = volatility_percent(&prices(&[90, 110]));
+        assert_eq!(volatility.round_dp(6), Decimal::from(10));
+    }
+
+    #[test]
+    fn deal_score_compares_against_every_day() {
+        let history = prices(&[100, 200, 300, 400]);
+        assert_eq!(deal_score(&history, Decimal::from(50)), 100);
+        assert_eq!(deal_score(&history, Decimal::from(500)), 0);
+        // Two days cost more, one the same
+        assert_eq!(deal_score(&history, Decimal::from(200)), 62);
+        assert_eq!(
+            deal_score(&prices(&[250, 250, 250]), Decimal::from(250)),
+            50
+        );
+        assert_eq!(deal_score(&[], Decimal::from(250)), 50);
+    }
+
+    #[test]
+    fn sale_cycle_finds_sale_starts_and_gap() {
+        let history = closes(&[1000, 1000, 800, 800, 1000, 1000, 1000, 850, 1000, 1000]);
+        let first = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+        assert_eq!(
+            sale_cycle(&history, Decimal::from(1000)),
+            (Some(first + chrono::Duration::days(7)), Some(5))
+        );
+    }
+
+    #[test]
+    fn sale_cycle_needs_two_sales_for_a_gap() {
+        let history = closes(&[1000, 1000, 800, 1000]);
+        let first = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+        assert_eq!(
+            sale_cycle(&history, Decimal::from(1000)),
+            (Some(first + chrono::Duration::days(2)), None)
+        );
+        assert_eq!(
+            sale_cycle(&closes(&[1000; 8]), Decimal::from(1000)),
+            (None, None)
+        );
+        assert_eq!(
+            sale_cycle(&closes(&[800]), Decimal::from(800)),
+            (None, None)
+        );
+        assert_eq!(sale_cycle(&BTreeMap::new(), Decimal::ZERO), (None, None));
+    }
+}
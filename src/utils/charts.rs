@@ -0,0 +1,141 @@
+use once_cell::sync::Lazy;
+use plotters::coord::types::RangedDateTime;
+use plotters::prelude::*;
+use plotters::style::register_font;
+use sea_orm::prelude::{DateTime, Decimal};
+use std::io::Cursor;
+
+const WIDTH: u32 = 800;
+const HEIGHT: u32 = 400;
+
+pub const CHART_CONTENT_ID: &str = "price-chart";
+pub const CHART_FILE_NAME: &str = "price-chart.png";
+
+// Bundled instead of looked up through fontconfig, so labels and the ₹ sign render the
+// same on every host
+const FONT: &[u8] = include_bytes!("../../assets/fonts/DejaVuSans.ttf");
+static FONT_REGISTERED: Lazy<Result<(), String>> = Lazy::new(|| {
+    register_font("sans-serif", FontStyle::Normal, FONT)
+        .map_err(|_| "Bundled chart font is not a valid font".to_string())
+});
+
+/// Draws the price history as a PNG line chart, marking the lowest and highest prices and,
+/// when set, the alert threshold as a dashed line. `history` may be in any order.
+pub fn price_chart(
+    history: &[(DateTime, Decimal)],
+    threshold: Option<Decimal>,
+) -> Result<Vec<u8>, String> {
+    // Zero prices are failed scrapes, not real prices
+    let mut points: Vec<(DateTime, f64)> = history
+        .iter()
+        .filter(|(_, price)| !price.is_zero())
+        .filter_map(|(recorded_at, price)| Some((*recorded_at, f64::try_from(*price).ok()?)))
+        .collect();
+    points.sort_by_key(|(recorded_at, _)| *recorded_at);
+    FONT_REGISTERED.clone()?;
+
+    let (Some(first), Some(last)) = (points.first().copied(), points.last().copied()) else {
+        return Err("No price history to chart yet".to_string());
+    };
+    if first.0 == last.0 {
+        return Err("Not enough price history to chart yet".to_string());
+    }
+
+    let lowest = points
+        .iter()
+        .copied()
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .unwrap_or(first);
+    let highest = points
+        .iter()
+        .copied()
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .unwrap_or(first);
+    let threshold = threshold
+        .filter(|threshold| !threshold.is_zero())
+        .and_then(|threshold| f64::try_from(threshold).ok());
+
+    let (mut low, mut high) = (lowest.1, highest.1);
+    if let Some(threshold) = threshold {
+        low = low.min(threshold);
+        high = high.max(threshold);
+    }
+    let padding = ((high - low) * 0.15).max(10.0);
+    // Labels of points in the right half go to the left so they stay inside the image
+    let midpoint = first.0 + (last.0 - first.0) / 2;
+
+    let mut buffer = vec![0u8; (WIDTH * HEIGHT * 3) as usize];
+    {
+        let root = BitMapBackend::with_buffer(&mut buffer, (WIDTH, HEIGHT)).into_drawing_area();
+        root.fill(&WHITE).map_err(|e| e.to_string())?;
+
+        let mut chart = ChartBuilder::on(&root)
+            .margin(20)
+            .x_label_area_size(35)
+            .y_label_area_size(70)
+            .build_cartesian_2d(
+                RangedDateTime::from(first.0..last.0),
+                (low - padding)..(high + padding),
+            )
+            .map_err(|e| e.to_string())?;
+
+        chart
+            .configure_mesh()
+            .light_line_style(RGBColor(240, 240, 240))
+            .x_labels(6)
+            .x_label_formatter(&|date| date.format("%d %b").to_string())
+            .y_label_formatter(&|price| format!("₹{:.0}", price))
+            .draw()
+            .map_err(|e| e.to_string())?;
+
+        chart
+            .draw_series(LineSeries::new(
+                points.iter().copied(),
+                RGBColor(33, 150, 243).stroke_width(2),
+            ))
+            .map_err(|e| e.to_string())?;
+
+        if let Some(threshold) = threshold {
+            chart
+                .draw_series(DashedLineSeries::new(
+                    [(first.0, threshold), (last.0, threshold)],
+                    8,
+                    6,
+                    RGBColor(255, 152, 0).stroke_width(2),
+                ))
+                .map_err(|e| e.to_string())?;
+        }
+
+        for (point, color) in [
+            (lowest, RGBColor(76, 175, 80)),
+            (highest, RGBColor(244, 67, 54)),
+        ] {
+            chart
+                .draw_series(PointSeries::of_element(
+                    [point],
+                    5,
+                    color.filled(),
+                    &|coord, size, style| {
+                        EmptyElement::at(coord)
+                            + Circle::new((0, 0), size, style)
+                            + Text::new(
+                                format!("₹{:.0}", coord.1),
+                                (if coord.0 > midpoint { -60 } else { 8 }, -18),
+                                ("sans-serif", 16).into_font(),
+                            )
+                    },
+                ))
+                .map_err(|e| e.to_string())?;
+        }
+
+        root.present().map_err(|e| e.to_string())?;
+    }
+
+    let image = image::RgbImage::from_raw(WIDTH, HEIGHT, buffer)
+        .ok_or_else(|| "Chart buffer has the wrong size".to_string())?;
+    let mut png = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut png), image::ImageOutputFormat::Png)
+        .map_err(|e| e.to_string())?;
+    Ok(png)
+}
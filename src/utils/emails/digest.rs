@@ -0,0 +1,170 @@
+use handlebars::Handlebars;
+use sea_orm::prelude::Decimal;
+use sea_orm::{ConnectionTrait, TransactionTrait};
+
+use crate::config::links::LinkConfig;
+use crate::cron::digests::Cadence;
+use crate::cron::notifications::percent_change;
+use crate::cron::outbox::enqueue_email;
+
+/// One tracked product in a digest.
+pub struct DigestRow {
+    pub product_name: String,
+    pub product_url: String,
+    pub current_price: Decimal,
+    /// Price before the first alert in this digest; unknown for brand new products.
+    pub previous_price: Option<Decimal>,
+    pub alerts: Vec<String>,
+    pub manage_url: Option<String>,
+}
+
+impl DigestRow {
+    fn change_percent(&self) -> Option<Decimal> {
+        self.previous_price
+            .filter(|previous| !previous.is_zero() && !self.current_price.is_zero())
+            .map(|previous| percent_change(previous, self.current_price))
+    }
+}
+
+/// All alerts of one recipient since their last digest, in a single email.
+pub struct DigestEmail {
+    to: String,
+    cadence: Option<Cadence>,
+    rows: Vec<DigestRow>,
+    /// Replaces the cadence based subject and the footer, for one-off summaries.
+    heading: Option<(String, String)>,
+}
+
+impl DigestEmail {
+    /// Rows are ordered by the size of their price change, biggest first, with unknown
+    /// changes last.
+    pub fn new(to: String, cadence: Option<Cadence>, mut rows: Vec<DigestRow>) -> Self {
+        rows.sort_by_key(|row| std::cmp::Reverse(row.change_percent().map(|change| change.abs())));
+        Self {
+            to,
+            cadence,
+            rows,
+            heading: None,
+        }
+    }
+
+    /// Sends the rows as a one-off summary under `subject`, with `footer` in place of
+    /// the note about digest settings.
+    pub fn heading(mut self, subject: String, footer: String) -> Self {
+        self.heading = Some((subject, footer));
+        self
+    }
+
+    fn subject(&self) -> String {
+        if let Some((subject, _)) = &self.heading {
+            return subject.clone();
+        }
+        let period = match self.cadence {
+            Some(Cadence::Daily) => "Daily price digest",
+            Some(Cadence::Weekly) => "Weekly price digest",
+            None => "Price digest",
+        };
+        match self.rows.len() {
+            1 => format!("{}: 1 product", period),
+            count => format!("{}: {} products", period, count),
+        }
+    }
+
+    /// Renders the email and hands it to the outbox; returns the outbox id.
+    pub async fn queue(
+        &self,
+        db: &(impl ConnectionTrait + TransactionTrait),
+    ) -> Result<i32, String> {
+        // Covers several trackers, so the unsubscribe link stops the whole address
+        let unsubscribe_url = LinkConfig::get().email_unsubscribe_url(&self.to);
+        let body = self.render(unsubscribe_url.as_deref())?;
+        enqueue_email(
+            db,
+            self.to.clone(),
+            self.subject(),
+            body,
+            unsubscribe_url,
+            Vec::new(),
+        )
+        .await
+        .map_err(|e| e.to_string())
+    }
+
+    fn render(&self, unsubscribe_url: Option<&str>) -> Result<String, String> {
+        let mut handlebars = Handlebars::new();
+
+        handlebars
+            .register_template_string(
+                "digest_template",
+                r#"<!DOCTYPE html>
+                <html>
+                <head>
+                <style>
+                .container { max-width: 600px; margin: auto; font-family: Arial, sans-serif; }
+                table { width: 100%; border-collapse: collapse; }
+                th, td { padding: 8px; border-bottom: 1px solid #eee; text-align: left; vertical-align: top; }
+                .price { font-weight: bold; color: #2196f3; }
+                .down { color: #4caf50; }
+                .up { color: #f44336; }
+                .alerts { color: #757575; font-size: 13px; }
+                .footer { color: #757575; font-size: 12px; margin-top: 20px; }
+                </style>
+                </head>
+                <body>
+                <div class="container">
+                <h2>{{title}}</h2>
+                <table>
+                <tr><th>Product</th><th>Price</th><th>Change</th></tr>
+                {{#each rows}}
+                <tr>
+                <td><a href="{{this.product_url}}">{{this.product_name}}</a>
+                <div class="alerts">{{this.alerts}}{{#if this.manage_url}} · <a href="{{this.manage_url}}">Manage</a>{{/if}}</div></td>
+                <td class="price">₹{{this.current_price}}</td>
+                <td class="{{this.direction}}">{{this.change}}</td>
+                </tr>
+                {{/each}}
+                </table>
+                <p class="footer">{{{footer}}}{{#if unsubscribe_url}} · <a href="{{unsubscribe_url}}">Unsubscribe from price alert emails</a>{{/if}}</p>
+                </div>
+                </body>
+                </html>"#,
+            )
+            .map_err(|e| e.to_string())?;
+
+        let data = serde_json::json!({
+            "title": self.subject(),
+            "unsubscribe_url": unsubscribe_url,
+            "footer": match &self.heading {
+                Some((_, footer)) => footer.as_str(),
+                None => "Use <code>/emaildigest</code> in Discord to change how often you get this summary, or to get every alert on its own again.",
+            },
+            "rows": self.rows
+                .iter()
+                .map(|row| {
+                    let change = row.change_percent().map(|change| change.round_dp(1));
+                    serde_json::json!({
+                        "product_name": row.product_name,
+                        "product_url": row.product_url,
+                        "current_price": row.current_price,
+                        "alerts": row.alerts.join(", "),
+                        "manage_url": row.manage_url,
+                        "change": match change {
+                            Some(change) if change > Decimal::ZERO => format!("+{}%", change),
+                            Some(change) => format!("{}%", change),
+                            None => "—".to_string(),
+                        },
+                        "direction": match change {
+                            Some(change) if change < Decimal::ZERO => "down",
+                            Some(change) if change > Decimal::ZERO => "up",
+                            _ => "",
+                        },
+                    })
+                })
+                .collect::<Vec<_>>()
+        });
+
+        handlebars
+            .render("digest_template", &data)
+            .map_err(|e| e.to_string())
+    }
+}
@@ -1 +1,3 @@
+pub mod digest;
 pub mod notify_price;
+pub mod verify_email;
@@ -1,8 +1,14 @@
 use handlebars::Handlebars;
 use sea_orm::prelude::DateTime;
 use sea_orm::prelude::Decimal;
+use sea_orm::DatabaseConnection;
 
-use crate::config::email::EmailConfig;
+use crate::config::email::InlineImage;
+use crate::cron::outbox::enqueue_email;
+use crate::utils::charts::CHART_CONTENT_ID;
+
+// The chart covers the full history; the table only lists the latest prices
+const RECENT_PRICES_SHOWN: usize = 10;
 
 pub struct PriceHistoryEmail {
     product_name: String,
@@ -11,6 +17,13 @@ pub struct PriceHistoryEmail {
     lowest_price: Decimal,
     price_history: Vec<(DateTime, Decimal)>,
     to: String,
+    image_url: Option<String>,
+    product_url: Option<String>,
+    alerts: Vec<String>,
+    manage_url: Option<String>,
+    unsubscribe_url: Option<String>,
+    chart: Option<Vec<u8>>,
+    analytics: Vec<(&'static str, String)>,
 }
 
 impl PriceHistoryEmail {
@@ -29,10 +42,81 @@ impl PriceHistoryEmail {
             lowest_price,
             price_history,
             to,
+            image_url: None,
+            product_url: None,
+            alerts: Vec::new(),
+            manage_url: None,
+            unsubscribe_url: None,
+            chart: None,
+            analytics: Vec::new(),
         }
     }
 
-    pub async fn send_price_history(&self) -> Result<(), String> {
+    pub fn product_details(mut self, image_url: Option<String>, product_url: String) -> Self {
+        self.image_url = image_url;
+        self.product_url = Some(product_url);
+        self
+    }
+
+    /// Rule messages shown above the price card; an email without alerts is a plain digest.
+    pub fn alerts(mut self, alerts: Vec<String>) -> Self {
+        self.alerts = alerts;
+        self
+    }
+
+    /// Footer links for pausing, snoozing or unsubscribing this preference.
+    pub fn preference_links(
+        mut self,
+        manage_url: Option<String>,
+        unsubscribe_url: Option<String>,
+    ) -> Self {
+        self.manage_url = manage_url;
+        self.unsubscribe_url = unsubscribe_url;
+        self
+    }
+
+    /// PNG price chart, embedded inline above the recent prices.
+    pub fn chart(mut self, png: Vec<u8>) -> Self {
+        self.chart = Some(png);
+        self
+    }
+
+    /// Deal score and trend figures, shown below the price card.
+    pub fn analytics(mut self, summary: Vec<(&'static str, String)>) -> Self {
+        self.analytics = summary;
+        self
+    }
+
+    fn subject(&self) -> String {
+        match self.alerts.first() {
+            Some(alert) => format!("{}: {}", self.product_name, alert),
+            None => "Price History Update".to_string(),
+        }
+    }
+
+    /// Renders the email and hands it to the outbox; returns the outbox id.
+    pub async fn queue_price_history(&self, db: &DatabaseConnection) -> Result<i32, String> {
+        let email_body = self.render()?;
+        enqueue_email(
+            db,
+            self.to.clone(),
+            self.subject(),
+            email_body,
+            self.unsubscribe_url.clone(),
+            self.chart
+                .iter()
+                .map(|png| InlineImage {
+                    content_id: CHART_CONTENT_ID.to_string(),
+                    content_type: "image/png".to_string(),
+                    data: png.clone(),
+                })
+                .collect(),
+        )
+        .await
+        .map_err(|e| e.to_string())
+    }
+
+    fn render(&self) -> Result<String, String> {
         let mut handlebars = Handlebars::new();
 
         handlebars
@@ -48,23 +132,46 @@ impl PriceHistoryEmail {
                 .history-item { padding: 10px; border-bottom: 1px solid #eee; }
                 .price { font-weight: bold; color: #2196f3; }
                 .highlight { color: #f44336; }
+                .alert { padding: 10px 15px; background-color: #fff3e0; border-left: 4px solid #ff9800; margin: 10px 0; }
+                .product-image { max-width: 200px; border-radius: 8px; }
+                .footer { color: #757575; font-size: 12px; margin-top: 20px; }
+                .chart { width: 100%; max-width: 600px; }
+                .analytics { background-color: #f1f8e9; }
                 </style>
                 </head>
                 <body>
                 <div class="container">
                 <h2>Price Information for {{product_name}}</h2>
+                {{#if image_url}}<img class="product-image" src="{{image_url}}" alt="{{product_name}}">{{/if}}
+                {{#if product_url}}<p><a href="{{product_url}}">View on Myntra</a></p>{{/if}}
+                {{#each alerts}}
+                <div class="alert">{{this}}</div>
+                {{/each}}
                 <div class="price-card current">
                 <h3>Current Price: <span class="price">₹{{current_price}}</span></h3>
                 <p>Highest Recorded: ₹{{highest_price}}</p>
                 <p>Lowest Recorded: ₹{{lowest_price}}</p>
                 </div>
+                {{#if analytics}}
+                <div class="price-card analytics">
+                <h3>Is now a good time to buy?</h3>
+                {{#each analytics}}
+                <p><strong>{{this.label}}:</strong> {{this.value}}</p>
+                {{/each}}
+                </div>
+                {{/if}}
                 <h3>Price History</h3>
+                {{#if chart_cid}}<img class="chart" src="cid:{{chart_cid}}" alt="Price chart">{{/if}}
+                <h4>Recent Prices</h4>
                 {{#each price_history}}
                 <div class="history-item">
                 <span>{{this.date}}</span>
                 <span class="price">₹{{this.price}}</span>
                 </div>
                 {{/each}}
+                {{#if unsubscribe_url}}
+                <p class="footer"><a href="{{manage_url}}">Pause or snooze these alerts</a> · <a href="{{unsubscribe_url}}">Unsubscribe</a></p>
+                {{/if}}
                 </div>
                 </body>
                 </html>"#,
@@ -73,11 +180,22 @@ impl PriceHistoryEmail {
 
         let data = serde_json::json!({
             "product_name": self.product_name,
+            "image_url": self.image_url,
+            "product_url": self.product_url,
+            "alerts": self.alerts,
+            "manage_url": self.manage_url,
+            "unsubscribe_url": self.unsubscribe_url,
+            "chart_cid": self.chart.as_ref().map(|_| CHART_CONTENT_ID),
             "current_price": self.current_price,
             "highest_price": self.highest_price,
             "lowest_price": self.lowest_price,
+            "analytics": self.analytics
+                .iter()
+                .map(|(label, value)| serde_json::json!({ "label": label, "value": value }))
+                .collect::<Vec<_>>(),
             "price_history": self.price_history
                 .iter()
+                .take(RECENT_PRICES_SHOWN)
                 .map(|(date, price)| {
                     serde_json::json!({
                         "date": date.format("%Y-%m-%d %H:%M").to_string(),
@@ -87,17 +205,8 @@ impl PriceHistoryEmail {
                 .collect::<Vec<_>>()
         });
 
-        let email_body = handlebars
+        handlebars
             .render("price_history_template", &data)
-            .map_err(|e| e.to_string())?;
-
-        EmailConfig::get()
-            .send_email(
-                self.to.clone(),
-                "Price History Update".to_string(),
-                email_body,
-            )
-            .await
             .map_err(|e| e.to_string())
     }
 }
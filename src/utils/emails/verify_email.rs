@@ -0,0 +1,247 @@
+use ::entity::{email_verifications, notification_preferences};
+use chrono::{Duration, Utc};
+use handlebars::Handlebars;
+use rand::Rng;
+use sea_orm::sea_query::Expr;
+use sea_orm::*;
+
+use crate::config::links::LinkConfig;
+use crate::cron::outbox::enqueue_email;
+
+const CODE_VALID_MINUTES: i64 = 60;
+// An address gets at most one code in this window, whoever asks for it
+const RESEND_WAIT_MINUTES: i64 = 10;
+// Wrong codes a user may enter before their pending codes stop working
+const MAX_FAILED_ATTEMPTS: i32 = 5;
+
+pub enum Verification {
+    Sent,
+    /// A code went to this address moments ago, so no new email was sent.
+    RecentlySent,
+}
+
+/// Creates (or refreshes) a pending verification for this Discord user and email and
+/// queues the confirmation code. Only an HMAC of the code is stored.
+pub async fn start_verification(
+    db: &DatabaseConnection,
+    discord_user_id: i64,
+    email: &str,
+) -> Result<Verification, String> {
+    let now = Utc::now().naive_utc();
+    let recently_sent = email_verifications::Entity::find()
+        .filter(email_verifications::Column::Email.eq(email))
+        .filter(
+            email_verifications::Column::SentAt.gt(now - Duration::minutes(RESEND_WAIT_MINUTES)),
+        )
+        .count(db)
+        .await
+        .map_err(|e| e.to_string())?;
+    if recently_sent > 0 {
+        return Ok(Verification::RecentlySent);
+    }
+
+    let code = rand::thread_rng().gen_range(100_000..1_000_000).to_string();
+    let code_hash = LinkConfig::get().hash_code(email, &code);
+    let expires_at = now + Duration::minutes(CODE_VALID_MINUTES);
+
+    let existing = email_verifications::Entity::find()
+        .filter(email_verifications::Column::DiscordUserId.eq(discord_user_id))
+        .filter(email_verifications::Column::Email.eq(email))
+        .one(db)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    match existing {
+        Some(row) => {
+            let mut update: email_verifications::ActiveModel = row.into();
+            update.code_hash = Set(code_hash);
+            update.expires_at = Set(expires_at);
+            update.failed_attempts = Set(0);
+            update.sent_at = Set(now);
+            update.update(db).await.map_err(|e| e.to_string())?;
+        }
+        None => {
+            email_verifications::ActiveModel {
+                discord_user_id: Set(discord_user_id),
+                email: Set(email.to_string()),
+                code_hash: Set(code_hash),
+                expires_at: Set(expires_at),
+                verified_at: Set(None),
+                created_at: Set(now),
+                failed_attempts: Set(0),
+                sent_at: Set(now),
+                ..Default::default()
+            }
+            .insert(db)
+            .await
+            .map_err(|e| e.to_string())?;
+        }
+    }
+
+    let body = render(&code)?;
+    enqueue_email(
+        db,
+        email.to_string(),
+        "Confirm your price alert email".to_string(),
+        body,
+        None,
+        Vec::new(),
+    )
+    .await
+    .map_err(|e| e.to_string())?;
+    Ok(Verification::Sent)
+}
+
+pub async fn is_verified(
+    db: &DatabaseConnection,
+    discord_user_id: i64,
+    email: &str,
+) -> Result<bool, DbErr> {
+    let verified = email_verifications::Entity::find()
+        .filter(email_verifications::Column::DiscordUserId.eq(discord_user_id))
+        .filter(email_verifications::Column::Email.eq(email))
+        .filter(email_verifications::Column::VerifiedAt.is_not_null())
+        .count(db)
+        .await?;
+    Ok(verified > 0)
+}
+
+/// The most recently verified address of a Discord user, reused when `/myntra` is run without an email.
+pub async fn verified_email_for(
+    db: &DatabaseConnection,
+    discord_user_id: i64,
+) -> Result<Option<String>, DbErr> {
+    Ok(email_verifications::Entity::find()
+        .filter(email_verifications::Column::DiscordUserId.eq(discord_user_id))
+        .filter(email_verifications::Column::VerifiedAt.is_not_null())
+        .order_by(email_verifications::Column::VerifiedAt, Order::Desc)
+        .one(db)
+        .await?
+        .map(|row| row.email))
+}
+
+/// Checks a code against the user's pending verifications. On success the email is marked
+/// verified, the user's trackers for it are switched on, unowned trackers for it become the
+/// user's, and the email is returned. A wrong
+/// code counts against every pending verification of the user; after
+/// `MAX_FAILED_ATTEMPTS` they expire and a new code has to be requested.
+pub async fn confirm_code(
+    db: &DatabaseConnection,
+    discord_user_id: i64,
+    code: &str,
+) -> Result<Option<String>, DbErr> {
+    let now = Utc::now().naive_utc();
+    let pending = email_verifications::Entity::find()
+        .filter(email_verifications::Column::DiscordUserId.eq(discord_user_id))
+        .filter(email_verifications::Column::VerifiedAt.is_null())
+        .filter(email_verifications::Column::ExpiresAt.gt(now))
+        .filter(email_verifications::Column::FailedAttempts.lt(MAX_FAILED_ATTEMPTS))
+        .all(db)
+        .await?;
+
+    let links = LinkConfig::get();
+    let pending_ids: Vec<i32> = pending.iter().map(|row| row.verification_id).collect();
+    let Some(row) = pending
+        .into_iter()
+        .find(|row| links.verify_code(&row.email, code.trim(), &row.code_hash))
+    else {
+        if !pending_ids.is_empty() {
+            record_failed_attempt(db, pending_ids, now).await?;
+        }
+        return Ok(None);
+    };
+
+    let email = row.email.clone();
+    let txn = db.begin().await?;
+
+    let mut update: email_verifications::ActiveModel = row.into();
+    update.verified_at = Set(Some(now));
+    // Burn the code so it cannot be replayed
+    update.expires_at = Set(now);
+    update.update(&txn).await?;
+
+    notification_preferences::Entity::update_many()
+        .filter(notification_preferences::Column::DiscordUserId.eq(discord_user_id))
+        .filter(notification_preferences::Column::Email.eq(&email))
+        .set(notification_preferences::ActiveModel {
+            active: Set(true),
+            updated_at: Set(now),
+            ..Default::default()
+        })
+        .exec(&txn)
+        .await?;
+
+    // Trackers added before Discord users were recorded go to whoever verifies their email
+    notification_preferences::Entity::update_many()
+        .filter(notification_preferences::Column::DiscordUserId.is_null())
+        .filter(notification_preferences::Column::Email.eq(&email))
+        .set(notification_preferences::ActiveModel {
+            discord_user_id: Set(Some(discord_user_id)),
+            updated_at: Set(now),
+            ..Default::default()
+        })
+        .exec(&txn)
+        .await?;
+
+    txn.commit().await?;
+    Ok(Some(email))
+}
+
+async fn record_failed_attempt(
+    db: &DatabaseConnection,
+    verification_ids: Vec<i32>,
+    now: chrono::NaiveDateTime,
+) -> Result<(), DbErr> {
+    let txn = db.begin().await?;
+    email_verifications::Entity::update_many()
+        .col_expr(
+            email_verifications::Column::FailedAttempts,
+            Expr::col(email_verifications::Column::FailedAttempts).add(1),
+        )
+        .filter(email_verifications::Column::VerificationId.is_in(verification_ids.clone()))
+        .exec(&txn)
+        .await?;
+    // Out of attempts: the code is dead even if the next guess would be right
+    email_verifications::Entity::update_many()
+        .col_expr(email_verifications::Column::ExpiresAt, Expr::value(now))
+        .filter(email_verifications::Column::VerificationId.is_in(verification_ids))
+        .filter(email_verifications::Column::FailedAttempts.gte(MAX_FAILED_ATTEMPTS))
+        .exec(&txn)
+        .await?;
+    txn.commit().await
+}
+
+fn render(code: &str) -> Result<String, String> {
+    let mut handlebars = Handlebars::new();
+
+    handlebars
+        .register_template_string(
+            "verify_email_template",
+            r#"<!DOCTYPE html>
+            <html>
+            <head>
+            <style>
+            .container { max-width: 600px; margin: auto; font-family: Arial, sans-serif; }
+            .code { font-size: 32px; font-weight: bold; letter-spacing: 6px; color: #2196f3; padding: 15px; border: 1px solid #ddd; border-radius: 8px; text-align: center; }
+            .muted { color: #757575; font-size: 13px; }
+            </style>
+            </head>
+            <body>
+            <div class="container">
+            <h2>Confirm your email for price alerts</h2>
+            <p>Someone (hopefully you) asked Affinity Bot to send price alerts to this address. Run <code>/verifyemail</code> in Discord with this code:</p>
+            <div class="code">{{code}}</div>
+            <p class="muted">The code expires in {{minutes}} minutes. If you did not request this, ignore this email and nothing will be sent.</p>
+            </div>
+            </body>
+            </html>"#,
+        )
+        .map_err(|e| e.to_string())?;
+
+    handlebars
+        .render(
+            "verify_email_template",
+            &serde_json::json!({ "code": code, "minutes": CODE_VALID_MINUTES }),
+        )
+        .map_err(|e| e.to_string())
+}
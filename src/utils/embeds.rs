@@ -0,0 +1,168 @@
+use entity::products;
+use sea_orm::prelude::Decimal;
+use serenity::all::{Color, CreateEmbed, CreateEmbedFooter, Timestamp};
+
+use crate::config::links::LinkConfig;
+use crate::cron::channels::AlertContext;
+use crate::scraper::sales::SaleDrop;
+use crate::utils::analytics::PriceAnalytics;
+use crate::utils::util::{product_display_name, product_url};
+
+// Longer lists get cut off in Discord's embed description
+const SUMMARY_LINES_SHOWN: usize = 15;
+
+/// Summary card for a tracked product, built from the stored `products` row.
+pub fn product_embed(product: &products::Model) -> CreateEmbed {
+    let mut embed = CreateEmbed::default()
+        .title(product_display_name(product))
+        .url(product_url(product))
+        .field("Price", format!("₹{}", product.current_price), true)
+        .color(if product.in_stock {
+            Color::DARK_GREEN
+        } else {
+            Color::DARK_GREY
+        })
+        .footer(CreateEmbedFooter::new(format!(
+            "Myntra product #{}",
+            product.product_id
+        )));
+
+    if let Some(mrp) = product.mrp.filter(|mrp| *mrp > product.current_price) {
+        embed = embed.field("MRP", format!("~~₹{}~~", mrp), true);
+    }
+    if let Some(discount) = product.discount_percent.filter(|discount| *discount > 0) {
+        embed = embed.field("Discount", format!("{}% off", discount), true);
+    }
+
+    embed = embed
+        .field("Lowest", format!("₹{}", product.lowest_price), true)
+        .field("Highest", format!("₹{}", product.highest_price), true)
+        .field(
+            "Stock",
+            if product.in_stock {
+                "In stock"
+            } else {
+                "Out of stock"
+            },
+            true,
+        );
+
+    if let Some(image_url) = &product.image_url {
+        embed = embed.thumbnail(image_url);
+    }
+    if let Ok(updated) = Timestamp::from_unix_timestamp(product.last_updated.and_utc().timestamp())
+    {
+        embed = embed.timestamp(updated);
+    }
+
+    embed
+}
+
+/// Deals channel post: the product card headed by why the price is worth a look.
+pub fn deal_embed(
+    product: &products::Model,
+    headline: &str,
+    previous_price: Decimal,
+) -> CreateEmbed {
+    product_embed(product)
+        .description(format!(
+            "{}\n~~₹{}~~ → **₹{}**",
+            headline, previous_price, product.current_price
+        ))
+        .color(Color::GOLD)
+}
+
+/// Watchlist post: the product card headed by what changed and the price before.
+pub fn watchlist_embed(
+    product: &products::Model,
+    headline: &str,
+    previous_price: Decimal,
+) -> CreateEmbed {
+    let price = if previous_price == product.current_price || previous_price.is_zero() {
+        format!("**₹{}**", product.current_price)
+    } else {
+        format!("~~₹{}~~ → **₹{}**", previous_price, product.current_price)
+    };
+    product_embed(product)
+        .description(format!("{}\n{}", headline, price))
+        .color(Color::TEAL)
+}
+
+/// Sale summary: the biggest drops of a store-wide sale, ranked, as one post.
+pub fn sale_embed(title: &str, summary: &str, drops: &[&SaleDrop<'_>]) -> CreateEmbed {
+    let lines: Vec<String> = drops
+        .iter()
+        .enumerate()
+        .map(|(rank, drop)| {
+            let (before, after) = (&drop.change.before, &drop.change.after);
+            format!(
+                "{}. [{}]({}) ~~₹{}~~ → **₹{}** (−{}%)",
+                rank + 1,
+                product_display_name(after),
+                product_url(after),
+                before.current_price,
+                after.current_price,
+                drop.percent.round_dp(0)
+            )
+        })
+        .collect();
+    summary_embed(title, summary, lines)
+}
+
+/// One post listing several products, one line each; long lists are cut off.
+pub fn summary_embed(title: &str, summary: &str, mut lines: Vec<String>) -> CreateEmbed {
+    if lines.len() > SUMMARY_LINES_SHOWN {
+        let more = lines.len() - SUMMARY_LINES_SHOWN;
+        lines.truncate(SUMMARY_LINES_SHOWN);
+        lines.push(format!("…and {} more", more));
+    }
+    CreateEmbed::new()
+        .title(title)
+        .description(format!("{}\n\n{}", summary, lines.join("\n")))
+        .color(Color::GOLD)
+        .timestamp(Timestamp::now())
+}
+
+/// Price alert for Discord DMs and channel posts: the product card with the fired rules
+/// on top and the change since the last alert.
+pub fn alert_embed(context: &AlertContext<'_>) -> CreateEmbed {
+    let current_price = context.current_price();
+    let mut description = context.messages().join("\n");
+    if description.is_empty() {
+        description = "Price update".to_string();
+    }
+    if let Some(manage_url) = LinkConfig::get().manage_url(context.preference_id) {
+        description.push_str(&format!("\n\n[Manage alerts]({})", manage_url));
+    }
+
+    let mut embed = product_embed(context.product).description(description);
+
+    if let Some(previous) = context
+        .previous_price
+        .filter(|previous| !previous.is_zero())
+    {
+        let change = current_price - previous;
+        let percent = (change / previous * Decimal::from(100)).round_dp(1);
+        let arrow = if change.is_sign_negative() {
+            "▼"
+        } else {
+            "▲"
+        };
+        embed = embed.field(
+            "Change",
+            format!("{} ₹{} ({}%)", arrow, change.abs(), percent),
+            true,
+        );
+    }
+
+    embed
+}
+
+/// Adds the deal score and trend figures below a product card.
+pub fn with_analytics(mut embed: CreateEmbed, analytics: &PriceAnalytics) -> CreateEmbed {
+    for (label, value) in analytics.summary() {
+        // The sale rhythm is a sentence and reads better on its own line
+        embed = embed.field(label, value, label != "Sales");
+    }
+    embed
+}
@@ -0,0 +1,188 @@
+use ::entity::{price_history, price_history_daily, products};
+use chrono::NaiveDate;
+use sea_orm::prelude::{DateTime, Decimal};
+use sea_orm::*;
+use serde::Serialize;
+
+use crate::utils::util::product_display_name;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+impl ExportFormat {
+    pub fn parse(format: &str) -> Option<Self> {
+        match format.to_ascii_lowercase().as_str() {
+            "csv" => Some(ExportFormat::Csv),
+            "json" => Some(ExportFormat::Json),
+            _ => None,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Json => "application/json",
+        }
+    }
+}
+
+/// One exported price point. Days the retention job has rolled up are exported as a
+/// single `daily` row holding the closing price; scrape details are not kept for those.
+#[derive(Serialize)]
+pub struct ExportRow {
+    pub product_id: i32,
+    pub product_name: String,
+    pub brand: Option<String>,
+    pub recorded_at: DateTime,
+    pub price: Decimal,
+    pub mrp: Option<Decimal>,
+    pub discount_percent: Option<i32>,
+    pub in_stock: Option<bool>,
+    pub granularity: &'static str,
+}
+
+pub struct PriceExport {
+    pub product: products::Model,
+    pub rows: Vec<ExportRow>,
+}
+
+impl PriceExport {
+    /// Price history of a product between `from` and `to`, both inclusive and both open
+    /// when `None`, oldest first. `None` when the product is not tracked.
+    pub async fn load(
+        db: &DatabaseConnection,
+        product_id: i32,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    ) -> Result<Option<Self>, DbErr> {
+        let Some(product) = products::Entity::find_by_id(product_id).one(db).await? else {
+            return Ok(None);
+        };
+        let product_name = product_display_name(&product);
+
+        let mut daily = price_history_daily::Entity::find()
+            .filter(price_history_daily::Column::ProductId.eq(product_id))
+            .order_by(price_history_daily::Column::Day, Order::Asc);
+        let mut raw = price_history::Entity::find()
+            .filter(price_history::Column::ProductId.eq(product_id))
+            .order_by(price_history::Column::RecordedAt, Order::Asc);
+        if let Some(from) = from {
+            daily = daily.filter(price_history_daily::Column::Day.gte(from));
+            raw = raw.filter(price_history::Column::RecordedAt.gte(start_of(from)));
+        }
+        if let Some(to) = to {
+            daily = daily.filter(price_history_daily::Column::Day.lte(to));
+            raw = raw.filter(
+                price_history::Column::RecordedAt.lt(start_of(to.succ_opt().unwrap_or(to))),
+            );
+        }
+
+        // Rolled-up days always come before the hourly rows that are still kept
+        let mut rows: Vec<ExportRow> = daily
+            .all(db)
+            .await?
+            .into_iter()
+            .filter_map(|row| {
+                Some(ExportRow {
+                    product_id,
+                    product_name: product_name.clone(),
+                    brand: product.brand.clone(),
+                    recorded_at: row.day.and_hms_opt(23, 59, 59)?,
+                    price: row.close_price,
+                    mrp: None,
+                    discount_percent: None,
+                    in_stock: None,
+                    granularity: "daily",
+                })
+            })
+            .collect();
+        rows.extend(raw.all(db).await?.into_iter().map(|row| ExportRow {
+            product_id,
+            product_name: product_name.clone(),
+            brand: product.brand.clone(),
+            recorded_at: row.recorded_at,
+            price: row.price,
+            mrp: row.mrp,
+            discount_percent: row.discount_percent,
+            in_stock: Some(row.in_stock),
+            granularity: "hourly",
+        }));
+
+        Ok(Some(Self { product, rows }))
+    }
+
+    pub fn file_name(&self, format: ExportFormat) -> String {
+        format!(
+            "price-history-{}.{}",
+            self.product.product_id,
+            format.extension()
+        )
+    }
+
+    pub fn render(&self, format: ExportFormat) -> Result<Vec<u8>, String> {
+        match format {
+            ExportFormat::Json => serde_json::to_vec_pretty(&self.rows).map_err(|e| e.to_string()),
+            ExportFormat::Csv => Ok(self.to_csv().into_bytes()),
+        }
+    }
+
+    fn to_csv(&self) -> String {
+        let mut csv = String::from(
+            "product_id,product_name,brand,recorded_at,price,mrp,discount_percent,in_stock,granularity\n",
+        );
+        for row in &self.rows {
+            let fields = [
+                row.product_id.to_string(),
+                csv_field(&row.product_name),
+                csv_field(row.brand.as_deref().unwrap_or_default()),
+                row.recorded_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+                row.price.to_string(),
+                row.mrp.map(|mrp| mrp.to_string()).unwrap_or_default(),
+                row.discount_percent
+                    .map(|discount| discount.to_string())
+                    .unwrap_or_default(),
+                row.in_stock
+                    .map(|in_stock| in_stock.to_string())
+                    .unwrap_or_default(),
+                row.granularity.to_string(),
+            ];
+            csv.push_str(&fields.join(","));
+            csv.push('\n');
+        }
+        csv
+    }
+}
+
+/// Parses a `YYYY-MM-DD` date from a command option or query string.
+pub fn parse_date(date: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d")
+        .map_err(|_| format!("`{}` is not a date like 2025-01-31", date))
+}
+
+fn start_of(day: NaiveDate) -> DateTime {
+    day.and_hms_opt(0, 0, 0).expect("midnight is a valid time")
+}
+
+// Quotes fields that would otherwise break the row, and defuses spreadsheet formulas
+fn csv_field(value: &str) -> String {
+    let value = if value.starts_with(['=', '+', '-', '@']) {
+        format!("'{}", value)
+    } else {
+        value.to_string()
+    };
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
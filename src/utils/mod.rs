@@ -1,2 +1,7 @@
+pub mod analytics;
+pub mod charts;
 pub mod emails;
+pub mod embeds;
+pub mod export;
 pub mod util;
+pub mod webhooks;
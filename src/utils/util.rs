@@ -1,7 +1,9 @@
+use entity::products;
 use serenity::all::{
     CommandInteraction, Context, CreateEmbed, CreateInteractionResponse,
-    CreateInteractionResponseMessage,
+    CreateInteractionResponseMessage, EditInteractionResponse,
 };
+use tracing::error;
 
 pub enum Response {
     Text(String),
@@ -24,13 +26,59 @@ pub async fn create_response(
     command: &CommandInteraction,
     response: impl Into<Response>,
 ) {
-    let response = response.into();
-    let builder = CreateInteractionResponse::Message(match response {
+    respond(ctx, command, response.into(), false).await
+}
+
+// Same as `create_response`, shown only to the member who ran the command
+pub async fn create_ephemeral_response(
+    ctx: &Context,
+    command: &CommandInteraction,
+    response: impl Into<Response>,
+) {
+    respond(ctx, command, response.into(), true).await
+}
+
+async fn respond(ctx: &Context, command: &CommandInteraction, response: Response, ephemeral: bool) {
+    let message = match response {
         Response::Text(content) => CreateInteractionResponseMessage::new().content(content),
         Response::Embed(embed) => CreateInteractionResponseMessage::new().embed(*embed),
-    });
+    };
+    let builder = CreateInteractionResponse::Message(message.ephemeral(ephemeral));
 
     if let Err(why) = command.create_response(&ctx.http, builder).await {
-        println!("Cannot respond to slash command: {why}");
+        error!("Cannot respond to slash command: {why}");
     }
 }
+
+// Same as `create_response`, for commands that deferred while doing slow work
+pub async fn edit_response(
+    ctx: &Context,
+    command: &CommandInteraction,
+    response: impl Into<Response>,
+) {
+    let builder = match response.into() {
+        Response::Text(content) => EditInteractionResponse::new().content(content),
+        Response::Embed(embed) => EditInteractionResponse::new().embed(*embed),
+    };
+
+    if let Err(why) = command.edit_response(&ctx.http, builder).await {
+        error!("Cannot edit slash command response: {why}");
+    }
+}
+
+// Human readable product title, falling back to the id until the first scrape fills in metadata
+pub fn product_display_name(product: &products::Model) -> String {
+    match (&product.brand, &product.name) {
+        (Some(brand), Some(name)) => format!("{} {}", brand, name),
+        (None, Some(name)) => name.clone(),
+        (Some(brand), None) => format!("{} (#{})", brand, product.product_id),
+        (None, None) => format!("Myntra product #{}", product.product_id),
+    }
+}
+
+pub fn product_url(product: &products::Model) -> String {
+    product
+        .canonical_url
+        .clone()
+        .unwrap_or_else(|| format!("https://www.myntra.com/{}", product.product_id))
+}
@@ -0,0 +1,71 @@
+use axum::extract::{Path, Query, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use sea_orm::DatabaseConnection;
+use serde::Deserialize;
+
+use crate::utils::export::{parse_date, ExportFormat, PriceExport};
+
+#[derive(Deserialize)]
+pub struct ExportQuery {
+    from: Option<String>,
+    to: Option<String>,
+    format: Option<String>,
+}
+
+/// `GET /export/:product_id?from=2025-01-01&to=2025-03-31&format=csv`, authorized with
+/// `Authorization: Bearer <EXPORT_API_TOKEN>`. Same data as `/pricehistory export`.
+pub async fn price_history(
+    State(db): State<DatabaseConnection>,
+    Path(product_id): Path<i32>,
+    Query(query): Query<ExportQuery>,
+    headers: HeaderMap,
+) -> Response {
+    if let Some(response) = super::unauthorized(&headers) {
+        return response;
+    }
+
+    let format = match query.format.as_deref() {
+        None => ExportFormat::Csv,
+        Some(format) => match ExportFormat::parse(format) {
+            Some(format) => format,
+            None => return (StatusCode::BAD_REQUEST, "format must be csv or json").into_response(),
+        },
+    };
+    let (from, to) = match (
+        query.from.as_deref().map(parse_date).transpose(),
+        query.to.as_deref().map(parse_date).transpose(),
+    ) {
+        (Ok(from), Ok(to)) => (from, to),
+        (Err(message), _) | (_, Err(message)) => {
+            return (StatusCode::BAD_REQUEST, message).into_response()
+        }
+    };
+
+    let export = match PriceExport::load(&db, product_id, from, to).await {
+        Ok(Some(export)) => export,
+        Ok(None) => return (StatusCode::NOT_FOUND, "Product is not tracked").into_response(),
+        Err(e) => {
+            tracing::error!("Failed to export price history for {}: {}", product_id, e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    match export.render(format) {
+        Ok(file) => (
+            [
+                (header::CONTENT_TYPE, format.content_type().to_string()),
+                (
+                    header::CONTENT_DISPOSITION,
+                    format!("attachment; filename=\"{}\"", export.file_name(format)),
+                ),
+            ],
+            file,
+        )
+            .into_response(),
+        Err(e) => {
+            tracing::error!("Failed to render price export for {}: {}", product_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
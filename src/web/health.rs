@@ -0,0 +1,24 @@
+use axum::http::HeaderMap;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+
+use super::unauthorized;
+use crate::scraper::browser::BrowserPool;
+use crate::scraper::rotation::Rotation;
+
+/// Browser pool counters for uptime checks and dashboards. Takes the export API token.
+pub async fn browsers(headers: HeaderMap) -> Response {
+    if let Some(response) = unauthorized(&headers) {
+        return response;
+    }
+    Json(BrowserPool::get().metrics()).into_response()
+}
+
+/// Per-proxy success, block and failure counts, by position in `SCRAPER_PROXIES`; empty
+/// without configured proxies. Takes the export API token.
+pub async fn proxies(headers: HeaderMap) -> Response {
+    if let Some(response) = unauthorized(&headers) {
+        return response;
+    }
+    Json(Rotation::get().metrics()).into_response()
+}
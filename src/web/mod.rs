@@ -0,0 +1,76 @@
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::Router;
+use sea_orm::DatabaseConnection;
+use std::net::SocketAddr;
+
+use crate::config::links::LinkConfig;
+
+mod export;
+mod health;
+mod preferences;
+
+/// Serves the links in notification emails: the preference page plus the pause, snooze
+/// and unsubscribe actions. Every route is keyed by a signed preference or address token,
+/// except the price export and the health checks, which take the export API token.
+pub async fn start(db: DatabaseConnection) {
+    let app = Router::new()
+        .route("/preferences/:token", get(preferences::manage))
+        .route(
+            "/preferences/:token/unsubscribe",
+            get(preferences::confirm_unsubscribe).post(preferences::unsubscribe),
+        )
+        .route("/preferences/:token/pause", post(preferences::pause))
+        .route("/preferences/:token/resume", post(preferences::resume))
+        .route("/preferences/:token/snooze", post(preferences::snooze))
+        .route(
+            "/emails/:token/unsubscribe",
+            get(preferences::confirm_email_unsubscribe).post(preferences::email_unsubscribe),
+        )
+        .route("/export/:product_id", get(export::price_history))
+        .route("/health/browsers", get(health::browsers))
+        .route("/health/proxies", get(health::proxies))
+        .with_state(db);
+
+    let addr = SocketAddr::from(([0, 0, 0, 0], LinkConfig::get().port()));
+    tokio::spawn(async move {
+        let listener = match tokio::net::TcpListener::bind(addr).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                tracing::error!("Could not bind preference server to {}: {}", addr, e);
+                return;
+            }
+        };
+        tracing::info!("Preference server listening on {}", addr);
+        if let Err(e) = axum::serve(listener, app).await {
+            tracing::error!("Preference server stopped: {}", e);
+        }
+    });
+}
+
+/// Checks `Authorization: Bearer <EXPORT_API_TOKEN>` and returns the response to send
+/// instead when it is missing or wrong. Without a configured token the protected routes
+/// do not exist.
+fn unauthorized(headers: &HeaderMap) -> Option<Response> {
+    let links = LinkConfig::get();
+    if !links.export_enabled() {
+        return Some((StatusCode::NOT_FOUND, "Not enabled").into_response());
+    }
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .unwrap_or_default();
+    if links.authorize_export(token.trim()) {
+        return None;
+    }
+    Some(
+        (
+            StatusCode::UNAUTHORIZED,
+            [(header::WWW_AUTHENTICATE, "Bearer")],
+            "Missing or invalid token",
+        )
+            .into_response(),
+    )
+}
@@ -0,0 +1,362 @@
+use ::entity::{email_digests, notification_preferences, products};
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{Html, IntoResponse, Response};
+use axum::Form;
+use chrono::Utc;
+use handlebars::Handlebars;
+use sea_orm::*;
+use serde::Deserialize;
+
+use crate::config::links::LinkConfig;
+use crate::utils::util::product_display_name;
+
+const MAX_SNOOZE_HOURS: i64 = 24 * 30;
+
+#[derive(Deserialize)]
+pub struct SnoozeForm {
+    hours: i64,
+}
+
+pub async fn manage(State(db): State<DatabaseConnection>, Path(token): Path<String>) -> Response {
+    match find_preference(&db, &token).await {
+        Ok(preference) => preference_page(&db, &token, preference, None).await,
+        Err(response) => response,
+    }
+}
+
+// Mail scanners follow GET links, so unsubscribing itself needs the POST below
+pub async fn confirm_unsubscribe(
+    State(db): State<DatabaseConnection>,
+    Path(token): Path<String>,
+) -> Response {
+    let preference = match find_preference(&db, &token).await {
+        Ok(preference) => preference,
+        Err(response) => return response,
+    };
+    let product_name = product_name(&db, preference.product_id).await;
+
+    render_page(
+        StatusCode::OK,
+        &serde_json::json!({
+            "title": "Unsubscribe",
+            "message": format!(
+                "Stop all price alerts for {} sent to {}?",
+                product_name,
+                recipient(&preference)
+            ),
+            "actions": [{
+                "url": format!("/preferences/{}/unsubscribe", token),
+                "label": "Unsubscribe",
+            }],
+        }),
+    )
+}
+
+/// Also the target of one-click `List-Unsubscribe-Post` requests from mail clients.
+pub async fn unsubscribe(
+    State(db): State<DatabaseConnection>,
+    Path(token): Path<String>,
+) -> Response {
+    let preference = match find_preference(&db, &token).await {
+        Ok(preference) => preference,
+        Err(response) => return response,
+    };
+    let product_name = product_name(&db, preference.product_id).await;
+
+    match notification_preferences::Entity::delete_by_id(preference.preference_id)
+        .exec(&db)
+        .await
+    {
+        Ok(_) => render_page(
+            StatusCode::OK,
+            &serde_json::json!({
+                "title": "Unsubscribed",
+                "message": format!(
+                    "You will no longer receive alerts for {} at {}.",
+                    product_name,
+                    recipient(&preference)
+                ),
+            }),
+        ),
+        Err(e) => server_error(e),
+    }
+}
+
+// Digests and other emails covering several trackers unsubscribe the whole address
+pub async fn confirm_email_unsubscribe(Path(token): Path<String>) -> Response {
+    let Some(email) = LinkConfig::get().verify_email(&token) else {
+        return link_not_found();
+    };
+    render_page(
+        StatusCode::OK,
+        &serde_json::json!({
+            "title": "Unsubscribe",
+            "message": format!("Stop all price alert emails to {}? Discord alerts are not affected.", email),
+            "actions": [{
+                "url": format!("/emails/{}/unsubscribe", token),
+                "label": "Unsubscribe",
+            }],
+        }),
+    )
+}
+
+/// Turns email off on every tracker sending to the address and ends its digest.
+pub async fn email_unsubscribe(
+    State(db): State<DatabaseConnection>,
+    Path(token): Path<String>,
+) -> Response {
+    let Some(email) = LinkConfig::get().verify_email(&token) else {
+        return link_not_found();
+    };
+
+    let unsubscribed = async {
+        let txn = db.begin().await?;
+        notification_preferences::Entity::update_many()
+            .filter(notification_preferences::Column::Email.eq(email.as_str()))
+            .set(notification_preferences::ActiveModel {
+                notify_email: Set(false),
+                updated_at: Set(Utc::now().naive_utc()),
+                ..Default::default()
+            })
+            .exec(&txn)
+            .await?;
+        email_digests::Entity::delete_many()
+            .filter(email_digests::Column::Email.eq(email.as_str()))
+            .exec(&txn)
+            .await?;
+        txn.commit().await
+    };
+    match unsubscribed.await {
+        Ok(()) => render_page(
+            StatusCode::OK,
+            &serde_json::json!({
+                "title": "Unsubscribed",
+                "message": format!("You will no longer receive price alert emails at {}.", email),
+            }),
+        ),
+        Err(e) => server_error(e),
+    }
+}
+
+pub async fn pause(State(db): State<DatabaseConnection>, Path(token): Path<String>) -> Response {
+    update_preference(
+        &db,
+        &token,
+        "Alerts paused until you resume them.",
+        |update| {
+            update.paused = Set(true);
+        },
+    )
+    .await
+}
+
+pub async fn resume(State(db): State<DatabaseConnection>, Path(token): Path<String>) -> Response {
+    update_preference(&db, &token, "Alerts resumed.", |update| {
+        update.paused = Set(false);
+        update.snoozed_until = Set(None);
+    })
+    .await
+}
+
+pub async fn snooze(
+    State(db): State<DatabaseConnection>,
+    Path(token): Path<String>,
+    Form(form): Form<SnoozeForm>,
+) -> Response {
+    let hours = form.hours.clamp(1, MAX_SNOOZE_HOURS);
+    let until = Utc::now().naive_utc() + chrono::Duration::hours(hours);
+    let notice = format!(
+        "Alerts snoozed until {} UTC.",
+        until.format("%Y-%m-%d %H:%M")
+    );
+    update_preference(&db, &token, &notice, |update| {
+        update.snoozed_until = Set(Some(until));
+    })
+    .await
+}
+
+async fn update_preference(
+    db: &DatabaseConnection,
+    token: &str,
+    notice: &str,
+    change: impl FnOnce(&mut notification_preferences::ActiveModel),
+) -> Response {
+    let preference = match find_preference(db, token).await {
+        Ok(preference) => preference,
+        Err(response) => return response,
+    };
+
+    let mut update: notification_preferences::ActiveModel = preference.into();
+    change(&mut update);
+    update.updated_at = Set(Utc::now().naive_utc());
+
+    match update.update(db).await {
+        Ok(preference) => preference_page(db, token, preference, Some(notice)).await,
+        Err(e) => server_error(e),
+    }
+}
+
+async fn find_preference(
+    db: &DatabaseConnection,
+    token: &str,
+) -> Result<notification_preferences::Model, Response> {
+    let Some(preference_id) = LinkConfig::get().verify(token) else {
+        return Err(link_not_found());
+    };
+    match notification_preferences::Entity::find_by_id(preference_id)
+        .one(db)
+        .await
+    {
+        Ok(Some(preference)) => Ok(preference),
+        Ok(None) => Err(link_not_found()),
+        Err(e) => Err(server_error(e)),
+    }
+}
+
+fn link_not_found() -> Response {
+    render_page(
+        StatusCode::NOT_FOUND,
+        &serde_json::json!({
+            "title": "Link not found",
+            "message": "This link is invalid, or the tracker it belongs to no longer exists.",
+        }),
+    )
+}
+
+async fn preference_page(
+    db: &DatabaseConnection,
+    token: &str,
+    preference: notification_preferences::Model,
+    notice: Option<&str>,
+) -> Response {
+    let product_name = product_name(db, preference.product_id).await;
+    let now = Utc::now().naive_utc();
+
+    let status = match preference.snoozed_until {
+        _ if preference.paused => "Paused".to_string(),
+        Some(until) if until > now => {
+            format!("Snoozed until {} UTC", until.format("%Y-%m-%d %H:%M"))
+        }
+        _ if !preference.active => "Waiting for email verification".to_string(),
+        _ => "Active".to_string(),
+    };
+
+    let base = format!("/preferences/{}", token);
+    let mut actions = Vec::new();
+    if preference.paused {
+        actions.push(
+            serde_json::json!({ "url": format!("{}/resume", base), "label": "Resume alerts" }),
+        );
+    } else {
+        actions
+            .push(serde_json::json!({ "url": format!("{}/pause", base), "label": "Pause alerts" }));
+    }
+    for (hours, label) in [(24, "Snooze for a day"), (24 * 7, "Snooze for a week")] {
+        actions.push(serde_json::json!({
+            "url": format!("{}/snooze", base),
+            "label": label,
+            "hours": hours,
+        }));
+    }
+    actions.push(
+        serde_json::json!({ "url": format!("{}/unsubscribe", base), "label": "Unsubscribe" }),
+    );
+
+    render_page(
+        StatusCode::OK,
+        &serde_json::json!({
+            "title": format!("Alerts for {}", product_name),
+            "notice": notice,
+            "message": format!("Sent to {}. Status: {}.", recipient(&preference), status),
+            "actions": actions,
+        }),
+    )
+}
+
+fn recipient(preference: &notification_preferences::Model) -> String {
+    let mut destinations = Vec::new();
+    if let Some(email) = preference
+        .email
+        .as_ref()
+        .filter(|_| preference.notify_email)
+    {
+        destinations.push(email.clone());
+    }
+    if preference.notify_dm {
+        destinations.push("your Discord DMs".to_string());
+    }
+    if preference.discord_channel_id.is_some() {
+        destinations.push("a Discord channel".to_string());
+    }
+    if preference.webhook_url.is_some() {
+        destinations.push("a webhook".to_string());
+    }
+    destinations.join(", ")
+}
+
+async fn product_name(db: &DatabaseConnection, product_id: i32) -> String {
+    match products::Entity::find_by_id(product_id).one(db).await {
+        Ok(Some(product)) => product_display_name(&product),
+        _ => format!("product {}", product_id),
+    }
+}
+
+fn server_error(e: DbErr) -> Response {
+    tracing::error!("Preference page failed: {}", e);
+    render_page(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        &serde_json::json!({
+            "title": "Something went wrong",
+            "message": "Please try the link again later.",
+        }),
+    )
+}
+
+fn render_page(status: StatusCode, data: &serde_json::Value) -> Response {
+    let mut handlebars = Handlebars::new();
+    let rendered = handlebars
+        .register_template_string(
+            "preference_page",
+            r#"<!DOCTYPE html>
+            <html>
+            <head>
+            <meta name="viewport" content="width=device-width, initial-scale=1">
+            <title>{{title}}</title>
+            <style>
+            .container { max-width: 600px; margin: 40px auto; font-family: Arial, sans-serif; }
+            .notice { padding: 10px 15px; background-color: #e8f5e9; border-left: 4px solid #4caf50; margin: 10px 0; }
+            form { display: inline-block; margin: 5px 5px 0 0; }
+            button { padding: 8px 14px; border: 1px solid #2196f3; border-radius: 6px; background: #fff; color: #2196f3; cursor: pointer; }
+            </style>
+            </head>
+            <body>
+            <div class="container">
+            <h2>{{title}}</h2>
+            {{#if notice}}<div class="notice">{{notice}}</div>{{/if}}
+            <p>{{message}}</p>
+            {{#each actions}}
+            <form method="post" action="{{this.url}}">
+            {{#if this.hours}}<input type="hidden" name="hours" value="{{this.hours}}">{{/if}}
+            <button type="submit">{{this.label}}</button>
+            </form>
+            {{/each}}
+            </div>
+            </body>
+            </html>"#,
+        )
+        .map_err(|e| e.to_string())
+        .and_then(|_| {
+            handlebars
+                .render("preference_page", data)
+                .map_err(|e| e.to_string())
+        });
+
+    match rendered {
+        Ok(html) => (status, Html(html)).into_response(),
+        Err(e) => {
+            tracing::error!("Could not render preference page: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}